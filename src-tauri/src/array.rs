@@ -0,0 +1,225 @@
+use bytes::{Buf, BufMut, BytesMut};
+use serde_json::Value;
+use std::error::Error;
+use tokio_postgres::types::{to_sql_checked, IsNull, Kind, ToSql, Type};
+
+type DecodeError = Box<dyn Error + Sync + Send>;
+
+// decodes postgres' binary array format into nested json arrays, one level per
+// dimension; lower bounds other than 1 are not kept
+pub fn decode(
+    member: &Type,
+    mut raw: &[u8],
+    element: impl Fn(&Type, &[u8]) -> Result<Value, DecodeError>,
+) -> Result<Value, DecodeError> {
+    if raw.remaining() < 12 {
+        return Err("invalid array message".into());
+    }
+    let dimensions = raw.get_i32();
+    let _has_nulls = raw.get_i32();
+    let _member_oid = raw.get_u32();
+
+    let dimensions = usize::try_from(dimensions).map_err(|_| "invalid array dimensions")?;
+    if raw.remaining() < dimensions * 8 {
+        return Err("invalid array message".into());
+    }
+    let mut lengths = Vec::with_capacity(dimensions);
+    for _ in 0..dimensions {
+        lengths.push(usize::try_from(raw.get_i32()).map_err(|_| "invalid array length")?);
+        let _lower_bound = raw.get_i32();
+    }
+
+    let count: usize = if lengths.is_empty() {
+        0
+    } else {
+        lengths.iter().product()
+    };
+    let mut elements = Vec::with_capacity(count);
+    for _ in 0..count {
+        if raw.remaining() < 4 {
+            return Err("invalid array message".into());
+        }
+        let length = raw.get_i32();
+        if length < 0 {
+            elements.push(Value::Null);
+            continue;
+        }
+        let length = length as usize;
+        if raw.remaining() < length {
+            return Err("invalid array message".into());
+        }
+        let (bytes, rest) = raw.split_at(length);
+        elements.push(element(member, bytes)?);
+        raw = rest;
+    }
+
+    Ok(nest(&lengths, &mut elements.into_iter()))
+}
+
+fn nest(lengths: &[usize], elements: &mut impl Iterator<Item = Value>) -> Value {
+    match lengths.split_first() {
+        None => Value::Array(Vec::new()),
+        Some((&length, [])) => Value::Array(elements.take(length).collect()),
+        Some((&length, inner)) => {
+            Value::Array((0..length).map(|_| nest(inner, elements)).collect())
+        }
+    }
+}
+
+// an array parameter built from json, with every element converted for the member type
+pub struct ArrayParam {
+    lengths: Vec<i32>,
+    elements: Vec<Box<dyn ToSql + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ArrayParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrayParam")
+            .field("lengths", &self.lengths)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArrayParam {
+    // nested json arrays become further dimensions and have to be rectangular;
+    // json and jsonb members take every element as a value, so those arrays are
+    // always one dimensional
+    pub fn from_json(value: &Value, member_type: &str) -> Result<Self, String> {
+        let Value::Array(items) = value else {
+            return Err(format!("expected a json array for {}[]", member_type));
+        };
+
+        let nested = member_type != "json" && member_type != "jsonb";
+        let mut lengths = Vec::new();
+        let mut level = items;
+        loop {
+            lengths.push(level.len());
+            match level.first() {
+                Some(Value::Array(inner)) if nested => level = inner,
+                _ => break,
+            }
+        }
+        if lengths.contains(&0) {
+            return Ok(ArrayParam {
+                lengths: Vec::new(),
+                elements: Vec::new(),
+            });
+        }
+
+        let mut elements = Vec::with_capacity(lengths.iter().product());
+        flatten(items, &lengths, nested, member_type, &mut elements)?;
+        Ok(ArrayParam {
+            lengths: lengths
+                .into_iter()
+                .map(|length| i32::try_from(length).map_err(|_| "array is too large".to_string()))
+                .collect::<Result<_, _>>()?,
+            elements,
+        })
+    }
+}
+
+fn flatten(
+    items: &[Value],
+    lengths: &[usize],
+    nested: bool,
+    member_type: &str,
+    elements: &mut Vec<Box<dyn ToSql + Send + Sync>>,
+) -> Result<(), String> {
+    let ragged = || "multi-dimensional arrays must have sub-arrays of matching lengths".to_string();
+    if items.len() != lengths[0] {
+        return Err(ragged());
+    }
+    for item in items {
+        match item {
+            Value::Array(inner) if nested && lengths.len() > 1 => {
+                flatten(inner, &lengths[1..], nested, member_type, elements)?
+            }
+            Value::Array(_) if nested => return Err(ragged()),
+            _ if lengths.len() > 1 => return Err(ragged()),
+            // json elements arrive as values already, so a string stays a json string;
+            // null is left as a NULL element, which is what it decodes from
+            _ if !nested && !item.is_null() => elements.push(Box::new(item.clone())),
+            _ => elements.push(crate::to_sql_value_literal(item, member_type)?),
+        }
+    }
+    Ok(())
+}
+
+impl ToSql for ArrayParam {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, DecodeError> {
+        let Kind::Array(member) = ty.kind() else {
+            return Err(format!("{} is not an array type", ty).into());
+        };
+
+        out.put_i32(i32::try_from(self.lengths.len())?);
+        let has_nulls_at = out.len();
+        out.put_i32(0);
+        out.put_u32(member.oid());
+        for length in &self.lengths {
+            out.put_i32(*length);
+            out.put_i32(1);
+        }
+
+        let mut has_nulls = false;
+        for element in &self.elements {
+            let start = out.len();
+            out.put_i32(0);
+            match element.to_sql_checked(member, out)? {
+                IsNull::Yes => {
+                    has_nulls = true;
+                    out.truncate(start);
+                    out.put_i32(-1);
+                }
+                IsNull::No => {
+                    let length = i32::try_from(out.len() - start - 4)?;
+                    out[start..start + 4].copy_from_slice(&length.to_be_bytes());
+                }
+            }
+        }
+        if has_nulls {
+            out[has_nulls_at..has_nulls_at + 4].copy_from_slice(&1i32.to_be_bytes());
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(ty.kind(), Kind::Array(_))
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: &Value, array_type: &Type) -> Value {
+        let Kind::Array(member) = array_type.kind() else {
+            unreachable!()
+        };
+        let param = ArrayParam::from_json(value, member.name()).unwrap();
+        let mut out = BytesMut::new();
+        param.to_sql(array_type, &mut out).unwrap();
+        decode(member, &out, crate::value_to_json).unwrap()
+    }
+
+    #[test]
+    fn jsonb_elements_round_trip_as_they_are() {
+        let value = serde_json::json!(["abc", "42", "", 42, {"a": [1, "b"]}, [1, 2], null]);
+        assert_eq!(round_trip(&value, &Type::JSONB_ARRAY), value);
+    }
+
+    #[test]
+    fn multi_dimensional_arrays_round_trip_with_nulls() {
+        let value = serde_json::json!([[1, null, 3], [4, 5, 6]]);
+        assert_eq!(round_trip(&value, &Type::INT4_ARRAY), value);
+        let value = serde_json::json!(["", null, "z"]);
+        assert_eq!(round_trip(&value, &Type::TEXT_ARRAY), value);
+    }
+
+    #[test]
+    fn rejects_ragged_arrays() {
+        let value = serde_json::json!([[1, 2], [3]]);
+        assert!(ArrayParam::from_json(&value, "int4").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Client;
+
+use crate::cell;
+use crate::error::Error;
+use crate::sql::{qualified_name, KnownColumns};
+
+// to_sql_value or to_sql_value_literal
+type ToSqlValue = fn(&Value, &str) -> Result<Box<dyn ToSql + Send + Sync>, String>;
+
+enum Cast {
+    // a type zubr can't bind, sent as text for the server to cast to this type
+    Text(String),
+    // a domain, sent as this base type and coerced to the domain by the server;
+    // the ToSql impls refuse a domain as the parameter type itself
+    Base(String),
+}
+
+// how values for a table's columns are bound when the udt name alone isn't enough
+#[derive(Default)]
+pub struct ParamCasts(HashMap<String, Cast>);
+
+impl ParamCasts {
+    // the casts target types without their typmod, so a too long value is rejected
+    // on assignment instead of being cut to fit by the cast
+    pub async fn load(
+        client: &Client,
+        schema: &str,
+        table: &str,
+        known: &KnownColumns<'_>,
+    ) -> Result<Self, Error> {
+        let query = "
+            SELECT a.attname::text, pg_catalog.format_type(a.atttypid, NULL), t.typtype = 'd',
+                pg_catalog.format_type(t.typbasetype, NULL)
+            FROM pg_catalog.pg_attribute AS a
+            JOIN pg_catalog.pg_type AS t ON t.oid = a.atttypid
+            WHERE a.attrelid = $1::text::regclass AND a.attnum > 0 AND NOT a.attisdropped;
+        ";
+
+        let rows = client
+            .query(query, &[&qualified_name(schema, table)])
+            .await?;
+
+        let mut casts = HashMap::new();
+        for row in &rows {
+            let column: String = row.get(0);
+            let is_domain: bool = row.get(2);
+            // udt names of domains are their base type's
+            let native = known.type_of(&column).is_ok_and(cell::is_native);
+            let cast = match (native, is_domain) {
+                (false, _) => Cast::Text(row.get(1)),
+                (true, true) => Cast::Base(row.get(3)),
+                (true, false) => continue,
+            };
+            casts.insert(column, cast);
+        }
+        Ok(ParamCasts(casts))
+    }
+
+    pub fn placeholder(&self, column: &str, n: usize) -> String {
+        match self.0.get(column) {
+            Some(Cast::Text(target)) => format!("${}::text::{}", n, target),
+            Some(Cast::Base(base)) => format!("${}::{}", n, base),
+            None => format!("${}", n),
+        }
+    }
+
+    // the value converted for the type its placeholder declares
+    pub fn param(
+        &self,
+        column: &str,
+        pg_type: &str,
+        value: &Value,
+    ) -> Result<Box<dyn ToSql + Send + Sync>, String> {
+        self.convert(column, pg_type, value, crate::to_sql_value)
+    }
+
+    // like param, but an empty string is bound as one instead of as NULL
+    pub fn literal(
+        &self,
+        column: &str,
+        pg_type: &str,
+        value: &Value,
+    ) -> Result<Box<dyn ToSql + Send + Sync>, String> {
+        self.convert(column, pg_type, value, crate::to_sql_value_literal)
+    }
+
+    fn convert(
+        &self,
+        column: &str,
+        pg_type: &str,
+        value: &Value,
+        to_sql: ToSqlValue,
+    ) -> Result<Box<dyn ToSql + Send + Sync>, String> {
+        match self.0.get(column) {
+            Some(Cast::Text(_)) => match value {
+                Value::Object(_) | Value::Array(_) => {
+                    to_sql(&Value::String(value.to_string()), "text")
+                }
+                _ => to_sql(value, "text"),
+            },
+            _ => to_sql(value, pg_type),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use bytes::{BufMut, BytesMut};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use tokio_postgres::{Row, Statement, Transaction};
+
+use crate::error::Error;
+
+// the udt names value_to_json decodes and to_sql_value binds by themselves, also as
+// array members; columns of any other type are read and written as text that the
+// server casts
+const NATIVE_TYPES: &[&str] = &[
+    "bool",
+    "int2",
+    "int4",
+    "int8",
+    "float4",
+    "float8",
+    "numeric",
+    "text",
+    "varchar",
+    "bpchar",
+    "name",
+    "uuid",
+    "date",
+    "timestamp",
+    "timestamptz",
+    "time",
+    "timetz",
+    "interval",
+    "json",
+    "jsonb",
+    "vector",
+];
+
+pub fn is_native(pg_type: &str) -> bool {
+    NATIVE_TYPES.contains(&pg_type.strip_prefix('_').unwrap_or(pg_type))
+}
+
+// what the grid gets for every cell, so a value that couldn't be read is never
+// mistaken for a NULL and edited over
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Cell {
+    Value {
+        value: Value,
+    },
+    Null,
+    // `text` is the server's ::text cast of the value, when it could make one
+    Undecodable {
+        pg_type: String,
+        text: Option<String>,
+    },
+}
+
+impl Cell {
+    // the json the ui sends back for this cell; undecodable cells have none
+    pub fn value(&self) -> Option<Value> {
+        match self {
+            Cell::Value { value } => Some(value.clone()),
+            Cell::Null => Some(Value::Null),
+            Cell::Undecodable { .. } => None,
+        }
+    }
+}
+
+// the undecoded bytes of any value: read to decode by type, and sent back as is
+// for the server to cast to text
+#[derive(Debug)]
+pub struct RawValue<'a>(pub &'a [u8]);
+
+impl<'a> FromSql<'a> for RawValue<'a> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawValue(raw))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+impl ToSql for RawValue<'_> {
+    fn to_sql(
+        &self,
+        _: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.put_slice(self.0);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+pub async fn read_rows(
+    transaction: &mut Transaction<'_>,
+    rows: &[Row],
+) -> Result<Vec<Vec<Cell>>, Error> {
+    // one text cast statement per type that turned up undecodable
+    let mut text_casts: HashMap<u32, Statement> = HashMap::new();
+    let mut cells = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut row_cells = Vec::with_capacity(row.len());
+        for (idx, column) in row.columns().iter().enumerate() {
+            let Some(raw) = row.try_get::<_, Option<RawValue>>(idx)? else {
+                row_cells.push(Cell::Null);
+                continue;
+            };
+            let cell = match crate::value_to_json(column.type_(), raw.0) {
+                Ok(value) => Cell::Value { value },
+                Err(_) => Cell::Undecodable {
+                    pg_type: column.type_().name().to_string(),
+                    text: text_cast(transaction, &mut text_casts, column.type_(), raw).await,
+                },
+            };
+            row_cells.push(cell);
+        }
+        cells.push(row_cells);
+    }
+    Ok(cells)
+}
+
+// the value goes back in the binary form it came in, typed as its own type, so
+// the server can print it whatever it is. each cast gets its own savepoint, as a
+// failed one would otherwise abort the transaction and every cast after it
+async fn text_cast(
+    transaction: &mut Transaction<'_>,
+    statements: &mut HashMap<u32, Statement>,
+    pg_type: &Type,
+    raw: RawValue<'_>,
+) -> Option<String> {
+    let savepoint = transaction.savepoint("text_cast").await.ok()?;
+    let statement = match statements.get(&pg_type.oid()) {
+        Some(statement) => Some(statement.clone()),
+        None => savepoint
+            .prepare_typed("SELECT $1::text", std::slice::from_ref(pg_type))
+            .await
+            .ok(),
+    };
+    let text = match &statement {
+        Some(statement) => savepoint.query_one(statement, &[&raw]).await.ok(),
+        None => None,
+    }
+    .map(|row| row.get(0));
+
+    if let Some(statement) = statement {
+        statements.insert(pg_type.oid(), statement);
+    }
+    match text {
+        Some(_) => savepoint.commit().await.ok()?,
+        None => savepoint.rollback().await.ok()?,
+    }
+    text
+}
@@ -0,0 +1,298 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+pub const MICROS_PER_SECOND: u64 = 1_000_000;
+pub const MICROS_PER_MINUTE: u64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: u64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: u64 = 24 * MICROS_PER_HOUR;
+// postgres takes utc offsets up to 15:59:59 either way
+const MAX_OFFSET: u32 = 15 * 3600 + 59 * 60 + 59;
+
+// hh:mm:ss with only as many fractional digits as it needs, the way postgres prints
+// times of day and the time part of intervals, whose hours can pass 24
+pub fn write_clock(f: &mut fmt::Formatter<'_>, micros: u64) -> fmt::Result {
+    write!(
+        f,
+        "{:02}:{:02}:{:02}",
+        micros / MICROS_PER_HOUR,
+        micros / MICROS_PER_MINUTE % 60,
+        micros / MICROS_PER_SECOND % 60
+    )?;
+    let fraction = micros % MICROS_PER_SECOND;
+    if fraction > 0 {
+        write!(f, ".{}", format!("{:06}", fraction).trim_end_matches('0'))?;
+    }
+    Ok(())
+}
+
+// hh:mm, hh:mm:ss or hh:mm:ss.ffffff as microseconds; digits past the sixth
+// fractional one are rounded off
+pub fn parse_clock(s: &str) -> Option<u64> {
+    let mut parts = s.split(':');
+    let hours = digits(parts.next()?)?;
+    let minutes = digits(parts.next()?)?;
+    let (seconds, fraction) = match parts.next() {
+        Some(part) => match part.split_once('.') {
+            Some((whole, fraction)) => (digits(whole)?, fraction_micros(fraction)?),
+            None => (digits(part)?, 0),
+        },
+        None => (0, 0),
+    };
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    hours
+        .checked_mul(MICROS_PER_HOUR)?
+        .checked_add(minutes * MICROS_PER_MINUTE + seconds * MICROS_PER_SECOND + fraction)
+}
+
+fn digits(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn fraction_micros(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let micros: u64 = format!("{:0<6}", &s[..s.len().min(6)]).parse().ok()?;
+    let round_up = s.as_bytes().get(6).is_some_and(|&d| d >= b'5');
+    Some(micros + u64::from(round_up))
+}
+
+// a time of day in microseconds since midnight; 24:00:00 itself is allowed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time(u64);
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_clock(f, self.0)
+    }
+}
+
+impl FromStr for Time {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_time(s.trim())
+            .map(Time)
+            .ok_or_else(|| "expected hh:mm[:ss[.ffffff]] up to 24:00:00".to_string())
+    }
+}
+
+fn parse_time(s: &str) -> Option<u64> {
+    parse_clock(s).filter(|&micros| micros <= MICROS_PER_DAY)
+}
+
+impl<'a> FromSql<'a> for Time {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 8 {
+            return Err("invalid time message".into());
+        }
+        Ok(Time(u64::try_from(raw.get_i64())?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::TIME
+    }
+}
+
+impl ToSql for Time {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_i64(i64::try_from(self.0)?);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::TIME
+    }
+
+    to_sql_checked!();
+}
+
+// a time of day with its utc offset in seconds, positive east of utc; postgres
+// sends the offset the other way round, as seconds west
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeTz {
+    time: u64,
+    offset: i32,
+}
+
+impl fmt::Display for TimeTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_clock(f, self.time)?;
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.unsigned_abs();
+        let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+        if seconds != 0 {
+            write!(f, "{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds)
+        } else if minutes != 0 {
+            write!(f, "{}{:02}:{:02}", sign, hours, minutes)
+        } else {
+            write!(f, "{}{:02}", sign, hours)
+        }
+    }
+}
+
+impl FromStr for TimeTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || "expected hh:mm[:ss[.ffffff]] and a utc offset like +05:30".to_string();
+        let s = s.trim();
+        let (time, offset) = match s.strip_suffix(['Z', 'z']) {
+            Some(time) => (time, Some(0)),
+            None => {
+                let at = s.rfind(['+', '-']).ok_or_else(invalid)?;
+                (&s[..at], parse_offset(&s[at..]))
+            }
+        };
+        Ok(TimeTz {
+            time: parse_time(time.trim_end()).ok_or_else(invalid)?,
+            offset: offset.ok_or_else(invalid)?,
+        })
+    }
+}
+
+// +hh, +hhmm, +hh:mm or +hh:mm:ss
+fn parse_offset(s: &str) -> Option<i32> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+')?),
+    };
+    let mut parts: Vec<&str> = unsigned.split(':').collect();
+    if parts.len() == 1 && unsigned.len() == 4 {
+        parts = vec![&unsigned[..2], &unsigned[2..]];
+    }
+    if parts.len() > 3 || parts[0].len() > 2 || parts.iter().skip(1).any(|part| part.len() != 2) {
+        return None;
+    }
+    let mut seconds = 0;
+    for (part, size) in parts.iter().zip([3600, 60, 1]) {
+        let value = digits(part)?;
+        if size < 3600 && value >= 60 {
+            return None;
+        }
+        seconds += value * size;
+    }
+    let seconds = u32::try_from(seconds).ok().filter(|&s| s <= MAX_OFFSET)?;
+    let seconds = i32::try_from(seconds).ok()?;
+    Some(if negative { -seconds } else { seconds })
+}
+
+impl<'a> FromSql<'a> for TimeTz {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 12 {
+            return Err("invalid timetz message".into());
+        }
+        let time = u64::try_from(raw.get_i64())?;
+        let offset = -raw.get_i32();
+        Ok(TimeTz { time, offset })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::TIMETZ
+    }
+}
+
+impl ToSql for TimeTz {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_i64(i64::try_from(self.time)?);
+        out.put_i32(-self.offset);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::TIMETZ
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_round_trip;
+
+    #[test]
+    fn time_round_trips() {
+        for (input, text) in [
+            ("00:00", "00:00:00"),
+            ("9:05", "09:05:00"),
+            ("13:45:30.5", "13:45:30.5"),
+            ("00:00:00.000001", "00:00:00.000001"),
+            ("23:59:59.9999995", "24:00:00"),
+            ("24:00:00", "24:00:00"),
+        ] {
+            let time: Time = input.parse().unwrap();
+            assert_eq!(time.to_string(), text);
+            assert_eq!(text.parse::<Time>(), Ok(time));
+            assert_eq!(binary_round_trip(&time, &Type::TIME), time);
+        }
+    }
+
+    #[test]
+    fn time_rejects_malformed() {
+        for input in [
+            "",
+            "12",
+            "24:00:01",
+            "12:60",
+            "12:00:60",
+            "12:00:00.",
+            "-01:00",
+        ] {
+            assert!(input.parse::<Time>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn timetz_round_trips() {
+        for (input, text) in [
+            ("12:34:56.789+05:30", "12:34:56.789+05:30"),
+            ("00:00-08", "00:00:00-08"),
+            ("12:00Z", "12:00:00+00"),
+            ("12:00:00 +0530", "12:00:00+05:30"),
+            ("23:59:59.999999-03:30:15", "23:59:59.999999-03:30:15"),
+        ] {
+            let time: TimeTz = input.parse().unwrap();
+            assert_eq!(time.to_string(), text);
+            assert_eq!(text.parse::<TimeTz>(), Ok(time));
+            assert_eq!(binary_round_trip(&time, &Type::TIMETZ), time);
+        }
+    }
+
+    #[test]
+    fn timetz_sends_the_offset_west_of_utc() {
+        let mut out = BytesMut::new();
+        let time: TimeTz = "01:00+01".parse().unwrap();
+        time.to_sql(&Type::TIMETZ, &mut out).unwrap();
+        assert_eq!(&out[8..], &(-3600i32).to_be_bytes());
+    }
+
+    #[test]
+    fn timetz_rejects_malformed() {
+        for input in ["12:00", "12:00+16", "12:00+05:60", "12:00+5:30:1", "+05"] {
+            assert!(input.parse::<TimeTz>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn timetz_rejects_oversized_offsets() {
+        for input in [
+            "12:00+99999999999999999",
+            "12:00-99999999999999999:00",
+            "12:00+015",
+            "12:00+15:59:60",
+        ] {
+            assert!(input.parse::<TimeTz>().is_err(), "{}", input);
+        }
+        let time: TimeTz = "12:00-15:59:59".parse().unwrap();
+        assert_eq!(time.to_string(), "12:00:00-15:59:59");
+    }
+}
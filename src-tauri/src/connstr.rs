@@ -0,0 +1,164 @@
+// helpers for the two libpq connection string formats, `postgresql://` uris and
+// `key=value` lists, used to handle keywords tokio_postgres does not understand
+
+pub fn strip_params(conn_str: &str, keys: &[&str]) -> (String, Vec<(String, String)>) {
+    let mut rest = String::with_capacity(conn_str.len());
+    let mut stripped = Vec::new();
+    let mut cursor = 0;
+
+    for param in params(conn_str) {
+        if keys.contains(&param.key.as_str()) {
+            rest.push_str(&conn_str[cursor..param.start]);
+            cursor = param.end;
+            stripped.push((param.key, param.value));
+        }
+    }
+    rest.push_str(&conn_str[cursor..]);
+
+    (tidy(&rest), stripped)
+}
+
+pub fn to_key_value<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    pairs
+        .into_iter()
+        .map(|(key, value)| {
+            format!(
+                "{}='{}'",
+                key,
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+struct ConnParam {
+    key: String,
+    value: String,
+    start: usize,
+    end: usize,
+}
+
+fn params(conn_str: &str) -> Vec<ConnParam> {
+    if conn_str.starts_with("postgres://") || conn_str.starts_with("postgresql://") {
+        uri_params(conn_str)
+    } else {
+        key_value_params(conn_str)
+    }
+}
+
+fn uri_params(uri: &str) -> Vec<ConnParam> {
+    let Some(query_start) = uri.find('?') else {
+        return Vec::new();
+    };
+
+    let mut params = Vec::new();
+    let mut start = query_start + 1;
+    for pair in uri[query_start + 1..].split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params.push(ConnParam {
+            key: percent_decode(key),
+            value: percent_decode(value),
+            start,
+            end: start + pair.len(),
+        });
+        start += pair.len() + 1;
+    }
+    params
+}
+
+fn key_value_params(s: &str) -> Vec<ConnParam> {
+    let bytes = s.as_bytes();
+    let mut params = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            break;
+        }
+
+        let start = i;
+        while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let key = s[start..i].to_string();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] != b'=' {
+            break;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        let mut value = String::new();
+        if i < bytes.len() && bytes[i] == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'\'' {
+                if bytes[i] == b'\\' && i + 1 < bytes.len() {
+                    i += 1;
+                }
+                let ch = s[i..].chars().next().unwrap();
+                value.push(ch);
+                i += ch.len_utf8();
+            }
+            i += 1;
+        } else {
+            let value_start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            value.push_str(&s[value_start..i]);
+        }
+
+        params.push(ConnParam {
+            key,
+            value,
+            start,
+            end: i.min(bytes.len()),
+        });
+    }
+
+    params
+}
+
+// drops the empty pairs and dangling "?" left behind by removed uri parameters
+fn tidy(s: &str) -> String {
+    if !s.starts_with("postgres://") && !s.starts_with("postgresql://") {
+        return s.trim().to_string();
+    }
+    let Some((base, query)) = s.split_once('?') else {
+        return s.to_string();
+    };
+    let pairs: Vec<&str> = query.split('&').filter(|pair| !pair.is_empty()).collect();
+    if pairs.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, pairs.join("&"))
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hi = (bytes[i + 1] as char).to_digit(16);
+            let lo = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hi, lo) {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
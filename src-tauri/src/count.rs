@@ -0,0 +1,70 @@
+use serde_json::Value;
+use std::time::Duration;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Transaction;
+
+use crate::error::Error;
+
+// COUNT(*) has to visit every matching row, so above this many estimated rows the
+// estimate is returned instead
+const EXACT_COUNT_LIMIT: i64 = 100_000;
+// the estimate can be far off for selective filters, so the exact count gets at
+// most this long before the estimate is used after all
+const EXACT_COUNT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct RowCount {
+    pub total: i64,
+    pub exact: bool,
+}
+
+// `from` is the statement after its select list, i.e. `FROM ... WHERE ...`, and
+// `params` are the ones its WHERE clause refers to
+pub async fn count_rows(
+    transaction: &mut Transaction<'_>,
+    from: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<RowCount, Error> {
+    // the planner's row estimate comes from pg_class.reltuples scaled to the
+    // table's current size and narrowed by the column statistics of the filters
+    let plan: Value = transaction
+        .query_one(&format!("EXPLAIN (FORMAT JSON) SELECT 1 {}", from), params)
+        .await?
+        .get(0);
+    let estimate = RowCount {
+        total: plan[0]["Plan"]["Plan Rows"].as_f64().unwrap_or_default() as i64,
+        exact: false,
+    };
+    if estimate.total > EXACT_COUNT_LIMIT {
+        return Ok(estimate);
+    }
+
+    // the savepoint keeps a failed count from aborting the page's transaction, and
+    // rolling it back restores the statement_timeout the page runs under
+    let savepoint = transaction.savepoint("exact_count").await?;
+    let total = exact_count(&savepoint, from, params).await;
+    savepoint.rollback().await?;
+    Ok(match total {
+        Ok(total) => RowCount { total, exact: true },
+        Err(_) => estimate,
+    })
+}
+
+async fn exact_count(
+    transaction: &Transaction<'_>,
+    from: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<i64, tokio_postgres::Error> {
+    // a shorter statement_timeout already in place is kept
+    transaction
+        .execute(
+            "SELECT pg_catalog.set_config('statement_timeout', LEAST(NULLIF(setting::bigint, 0), $1)::text, true)
+            FROM pg_catalog.pg_settings WHERE name = 'statement_timeout'",
+            &[&(EXACT_COUNT_TIMEOUT.as_millis() as i64)],
+        )
+        .await?;
+    Ok(transaction
+        .query_one(&format!("SELECT COUNT(*) {}", from), params)
+        .await?
+        .get(0))
+}
@@ -0,0 +1,230 @@
+use deadpool_postgres::PoolError;
+use tokio_postgres::error::{DbError, ErrorPosition};
+
+use crate::tls;
+
+// what every command rejects with; the ui gets `{ kind, message, ... }`
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Error {
+    Connection {
+        message: String,
+        code: Option<String>,
+    },
+    Tls {
+        message: String,
+    },
+    Auth {
+        message: String,
+        code: Option<String>,
+    },
+    // boxed so the common variants keep results small
+    Query(Box<QueryError>),
+    Validation {
+        message: String,
+        column: Option<String>,
+        row: Option<usize>,
+    },
+    NotFound {
+        message: String,
+    },
+    Cancelled {
+        message: String,
+    },
+    ReadOnly {
+        message: String,
+    },
+    // a local file zubr keeps, like the vault, could not be read or written
+    Io {
+        message: String,
+    },
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct QueryError {
+    pub code: Option<String>,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub constraint: Option<String>,
+    pub datatype: Option<String>,
+    // 1-based character offset into the statement
+    pub position: Option<u32>,
+    // index into the batch that failed, for commands that run several statements
+    pub row: Option<usize>,
+}
+
+impl Error {
+    pub fn connection(message: impl Into<String>) -> Self {
+        Error::Connection {
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    pub fn tls(message: impl Into<String>) -> Self {
+        Error::Tls {
+            message: message.into(),
+        }
+    }
+
+    pub fn auth(message: impl Into<String>) -> Self {
+        Error::Auth {
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Error::Validation {
+            message: message.into(),
+            column: None,
+            row: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Error::NotFound {
+            message: message.into(),
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        Error::Cancelled {
+            message: "query cancelled".to_string(),
+        }
+    }
+
+    pub fn read_only(message: impl Into<String>) -> Self {
+        Error::ReadOnly {
+            message: message.into(),
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Error::Io {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Connection { message, .. }
+            | Error::Tls { message }
+            | Error::Auth { message, .. }
+            | Error::Validation { message, .. }
+            | Error::NotFound { message }
+            | Error::Cancelled { message }
+            | Error::ReadOnly { message }
+            | Error::Io { message } => message,
+            Error::Query(query) => &query.message,
+        }
+    }
+
+    // ties a failure inside a batch to the row, and column when known, that caused it
+    pub fn at(mut self, row_index: usize, column_name: Option<&str>) -> Self {
+        match &mut self {
+            Error::Query(query) => {
+                query.row = Some(row_index);
+                if query.column.is_none() {
+                    query.column = column_name.map(str::to_string);
+                }
+            }
+            Error::Validation { row, column, .. } => {
+                *row = Some(row_index);
+                if column.is_none() {
+                    *column = column_name.map(str::to_string);
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+
+    fn from_db(db: &DbError) -> Self {
+        let code = db.code().code();
+        let message = db.message().to_string();
+        match code {
+            // invalid_authorization_specification and invalid_password
+            _ if code.starts_with("28") => Error::Auth {
+                message,
+                code: Some(code.to_string()),
+            },
+            // connection_exception, too_many_connections, the shutdown and
+            // cannot_connect_now states, and an unknown database
+            _ if code.starts_with("08")
+                || code.starts_with("57P")
+                || code == "53300"
+                || code == "3D000" =>
+            {
+                Error::Connection {
+                    message,
+                    code: Some(code.to_string()),
+                }
+            }
+            _ => Error::Query(Box::new(QueryError {
+                code: Some(code.to_string()),
+                message,
+                detail: db.detail().map(str::to_string),
+                hint: db.hint().map(str::to_string),
+                schema: db.schema().map(str::to_string),
+                table: db.table().map(str::to_string),
+                column: db.column().map(str::to_string),
+                constraint: db.constraint().map(str::to_string),
+                datatype: db.datatype().map(str::to_string),
+                position: match db.position() {
+                    Some(ErrorPosition::Original(position)) => Some(*position),
+                    _ => None,
+                },
+                row: None,
+            })),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<tokio_postgres::Error> for Error {
+    fn from(err: tokio_postgres::Error) -> Self {
+        if let Some(db) = err.as_db_error() {
+            return Error::from_db(db);
+        }
+        if let Some(message) = tls::tls_failure(&err) {
+            return Error::tls(message);
+        }
+        let io_failure = std::error::Error::source(&err)
+            .is_some_and(|source| source.downcast_ref::<std::io::Error>().is_some());
+        if err.is_closed() || io_failure {
+            return Error::connection(err.to_string());
+        }
+        Error::Query(Box::new(QueryError {
+            message: err.to_string(),
+            ..Default::default()
+        }))
+    }
+}
+
+impl From<PoolError> for Error {
+    fn from(err: PoolError) -> Self {
+        match err {
+            // anything that goes wrong while opening a connection is a connection problem
+            PoolError::Backend(err) => match Error::from(err) {
+                Error::Query(query) => Error::Connection {
+                    message: query.message,
+                    code: query.code,
+                },
+                err => err,
+            },
+            err => Error::connection(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use serde_json::Value;
+use tokio_postgres::types::ToSql;
+
+use crate::cast::ParamCasts;
+use crate::error::Error;
+use crate::sql::{quote_ident, KnownColumns, TEXT_TYPES};
+
+pub type Params = Vec<Box<dyn ToSql + Send + Sync>>;
+
+// the only operators a filter may use; anything else fails to deserialize
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterOperator {
+    #[serde(rename = "=")]
+    Eq,
+    #[serde(rename = "<>", alias = "!=")]
+    NotEq,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    LtEq,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    GtEq,
+    #[serde(rename = "LIKE")]
+    Like,
+    #[serde(rename = "NOT LIKE")]
+    NotLike,
+    #[serde(rename = "ILIKE")]
+    ILike,
+    #[serde(rename = "NOT ILIKE")]
+    NotILike,
+    #[serde(rename = "IS NULL")]
+    IsNull,
+    #[serde(rename = "IS NOT NULL")]
+    IsNotNull,
+    #[serde(rename = "IN")]
+    In,
+    #[serde(rename = "NOT IN")]
+    NotIn,
+    #[serde(rename = "BETWEEN")]
+    Between,
+    #[serde(rename = "NOT BETWEEN")]
+    NotBetween,
+}
+
+impl FilterOperator {
+    fn is_pattern(self) -> bool {
+        matches!(
+            self,
+            FilterOperator::Like
+                | FilterOperator::NotLike
+                | FilterOperator::ILike
+                | FilterOperator::NotILike
+        )
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            FilterOperator::Eq => "=",
+            FilterOperator::NotEq => "<>",
+            FilterOperator::Lt => "<",
+            FilterOperator::LtEq => "<=",
+            FilterOperator::Gt => ">",
+            FilterOperator::GtEq => ">=",
+            FilterOperator::Like => "LIKE",
+            FilterOperator::NotLike => "NOT LIKE",
+            FilterOperator::ILike => "ILIKE",
+            FilterOperator::NotILike => "NOT ILIKE",
+            FilterOperator::IsNull => "IS NULL",
+            FilterOperator::IsNotNull => "IS NOT NULL",
+            FilterOperator::In => "IN",
+            FilterOperator::NotIn => "NOT IN",
+            FilterOperator::Between => "BETWEEN",
+            FilterOperator::NotBetween => "NOT BETWEEN",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogicalOperator {
+    #[default]
+    #[serde(rename = "AND")]
+    And,
+    #[serde(rename = "OR")]
+    Or,
+}
+
+impl LogicalOperator {
+    fn sql(self) -> &'static str {
+        match self {
+            LogicalOperator::And => " AND ",
+            LogicalOperator::Or => " OR ",
+        }
+    }
+}
+
+// `value` is used by the single value operators, `values` by IN and BETWEEN
+#[derive(serde::Deserialize, Debug)]
+pub struct Filter {
+    column: String,
+    operator: FilterOperator,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    values: Vec<String>,
+}
+
+impl Filter {
+    fn condition(
+        &self,
+        known: &KnownColumns,
+        casts: &ParamCasts,
+        params: &mut Params,
+    ) -> Result<String, Error> {
+        let pg_type = known.type_of(&self.column)?;
+        let operator = self.operator.sql();
+
+        // pattern operators only exist for text, so other columns are matched on their text form
+        if self.operator.is_pattern() {
+            let value = self.single_value()?;
+            params.push(Box::new(value.clone()));
+            return Ok(format!(
+                "{}::text {} ${}",
+                quote_ident(&self.column),
+                operator,
+                params.len()
+            ));
+        }
+
+        let column = quote_ident(&self.column);
+        let mut bind = |value: &String| -> Result<String, Error> {
+            if value.is_empty() && !TEXT_TYPES.contains(&pg_type) {
+                return Err(self.invalid("an empty value only applies to text columns"));
+            }
+            let param = casts
+                .literal(&self.column, pg_type, &Value::String(value.clone()))
+                .map_err(|problem| self.invalid(&problem))?;
+            params.push(param);
+            Ok(casts.placeholder(&self.column, params.len()))
+        };
+
+        match self.operator {
+            FilterOperator::IsNull | FilterOperator::IsNotNull => {
+                Ok(format!("{} {}", column, operator))
+            }
+            FilterOperator::In | FilterOperator::NotIn => {
+                if self.values.is_empty() {
+                    return Err(self.invalid("needs at least one value"));
+                }
+                let placeholders = self
+                    .values
+                    .iter()
+                    .map(bind)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!(
+                    "{} {} ({})",
+                    column,
+                    operator,
+                    placeholders.join(", ")
+                ))
+            }
+            FilterOperator::Between | FilterOperator::NotBetween => {
+                let [low, high] = self.values.as_slice() else {
+                    return Err(self.invalid("needs exactly two values"));
+                };
+                Ok(format!(
+                    "{} {} {} AND {}",
+                    column,
+                    operator,
+                    bind(low)?,
+                    bind(high)?
+                ))
+            }
+            _ => {
+                let value = self.single_value()?;
+                Ok(format!("{} {} {}", column, operator, bind(value)?))
+            }
+        }
+    }
+
+    fn single_value(&self) -> Result<&String, Error> {
+        self.value
+            .as_ref()
+            .ok_or_else(|| self.invalid("needs a value"))
+    }
+
+    fn invalid(&self, problem: &str) -> Error {
+        Error::Validation {
+            message: format!(
+                "{} filter on {}: {}",
+                self.operator.sql(),
+                self.column,
+                problem
+            ),
+            column: Some(self.column.clone()),
+            row: None,
+        }
+    }
+}
+
+// a tree node: either a single condition or a nested group
+#[derive(serde::Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterNode {
+    Condition(Filter),
+    Group(FilterGroup),
+}
+
+// conditions and subgroups joined by one logical operator, optionally negated as a whole
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct FilterGroup {
+    #[serde(default)]
+    logical_operator: LogicalOperator,
+    #[serde(default)]
+    not: bool,
+    #[serde(default)]
+    filters: Vec<FilterNode>,
+}
+
+impl FilterGroup {
+    // empty groups, at any depth, don't restrict anything and compile to nothing
+    fn compile(
+        &self,
+        known: &KnownColumns,
+        casts: &ParamCasts,
+        params: &mut Params,
+    ) -> Result<Option<String>, Error> {
+        let mut parts = Vec::with_capacity(self.filters.len());
+        for node in &self.filters {
+            let part = match node {
+                FilterNode::Condition(filter) => Some(filter.condition(known, casts, params)?),
+                FilterNode::Group(group) => group.compile(known, casts, params)?,
+            };
+            parts.extend(part);
+        }
+        if parts.is_empty() {
+            return Ok(None);
+        }
+
+        let expression = format!("({})", parts.join(self.logical_operator.sql()));
+        if self.not {
+            Ok(Some(format!("NOT {}", expression)))
+        } else {
+            Ok(Some(expression))
+        }
+    }
+}
+
+// the condition the filters compile to, with every value bound as a parameter
+// after those already in `params`; None when nothing is filtered
+pub fn condition(
+    group: Option<&FilterGroup>,
+    known: &KnownColumns,
+    casts: &ParamCasts,
+    params: &mut Params,
+) -> Result<Option<String>, Error> {
+    match group {
+        Some(group) => group.compile(known, casts, params),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use serde_json::json;
+    use tokio_postgres::types::Type;
+
+    fn known() -> KnownColumns<'static> {
+        KnownColumns::new([("id", "int4"), ("name", "text"), ("qty", "int4")])
+    }
+
+    fn compile(group: Value, params: &mut Params) -> Result<Option<String>, Error> {
+        let group: FilterGroup = serde_json::from_value(group).unwrap();
+        condition(Some(&group), &known(), &ParamCasts::default(), params)
+    }
+
+    fn filter(column: &str, operator: &str, value: Value) -> Value {
+        match value {
+            Value::Array(values) => json!({
+                "type": "condition", "column": column, "operator": operator, "values": values,
+            }),
+            Value::Null => json!({ "type": "condition", "column": column, "operator": operator }),
+            value => json!({
+                "type": "condition", "column": column, "operator": operator, "value": value,
+            }),
+        }
+    }
+
+    fn group(logical_operator: &str, not: bool, filters: Vec<Value>) -> Value {
+        json!({ "type": "group", "logical_operator": logical_operator, "not": not, "filters": filters })
+    }
+
+    fn int4(param: &(dyn ToSql + Send + Sync)) -> i32 {
+        let mut out = BytesMut::new();
+        param.to_sql_checked(&Type::INT4, &mut out).unwrap();
+        i32::from_be_bytes(out[..].try_into().unwrap())
+    }
+
+    #[test]
+    fn numbers_placeholders_across_nested_groups() {
+        // a parameter bound before the filters, like the table data query's own
+        let mut params: Params = vec![Box::new(0i32)];
+        let sql = compile(
+            group(
+                "AND",
+                false,
+                vec![
+                    filter("id", "=", json!("1")),
+                    group(
+                        "OR",
+                        false,
+                        vec![
+                            filter("name", "LIKE", json!("a%")),
+                            group(
+                                "AND",
+                                true,
+                                vec![
+                                    filter("id", "IN", json!(["2", "3"])),
+                                    filter("qty", "BETWEEN", json!(["4", "5"])),
+                                ],
+                            ),
+                        ],
+                    ),
+                    group("OR", true, vec![]),
+                ],
+            ),
+            &mut params,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sql.as_deref(),
+            Some(
+                r#"("id" = $2 AND ("name"::text LIKE $3 OR NOT ("id" IN ($4, $5) AND "qty" BETWEEN $6 AND $7)))"#
+            )
+        );
+        assert_eq!(params.len(), 7);
+        let ints: Vec<i32> = [0, 1, 3, 4, 5, 6]
+            .iter()
+            .map(|&i| int4(params[i].as_ref()))
+            .collect();
+        assert_eq!(ints, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn empty_groups_filter_nothing() {
+        let mut params = Params::new();
+        let nested_empty = group("AND", true, vec![group("OR", false, vec![])]);
+        assert_eq!(compile(nested_empty, &mut params).unwrap(), None);
+        assert!(params.is_empty());
+        assert_eq!(
+            condition(None, &known(), &ParamCasts::default(), &mut params).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn negates_a_whole_group() {
+        let mut params = Params::new();
+        let sql = compile(
+            group(
+                "OR",
+                true,
+                vec![
+                    filter("name", "IS NULL", Value::Null),
+                    filter("qty", "<", json!("0")),
+                ],
+            ),
+            &mut params,
+        )
+        .unwrap();
+        assert_eq!(
+            sql.as_deref(),
+            Some(r#"NOT ("name" IS NULL OR "qty" < $1)"#)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_missing_values() {
+        for bad in [
+            filter("nope", "=", json!("1")),
+            filter("qty", "BETWEEN", json!(["1"])),
+            filter("qty", "IN", json!([])),
+            filter("qty", "=", Value::Null),
+            filter("qty", "=", json!("")),
+            filter("qty", "=", json!("x")),
+        ] {
+            let mut params = Params::new();
+            let result = compile(group("AND", false, vec![bad.clone()]), &mut params);
+            assert!(result.is_err(), "{}", bad);
+        }
+    }
+}
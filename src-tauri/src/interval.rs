@@ -0,0 +1,434 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+use crate::clock::{
+    parse_clock, write_clock, MICROS_PER_DAY, MICROS_PER_HOUR, MICROS_PER_MINUTE, MICROS_PER_SECOND,
+};
+
+// an interval as postgres keeps it: months, days and microseconds each with their
+// own sign, since neither a month nor a day has a fixed length
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+// printed the way postgres does with its default intervalstyle, e.g.
+// `1 year 2 mons -3 days +04:05:06.789`
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut after_negative = false;
+        for (value, unit) in [
+            (self.months / 12, "year"),
+            (self.months % 12, "mon"),
+            (self.days, "day"),
+        ] {
+            if value == 0 {
+                continue;
+            }
+            write!(
+                f,
+                "{}{}{} {}{}",
+                if first { "" } else { " " },
+                if after_negative && value > 0 { "+" } else { "" },
+                value,
+                unit,
+                if value != 1 { "s" } else { "" }
+            )?;
+            after_negative = value < 0;
+            first = false;
+        }
+        if first || self.micros != 0 {
+            let sign = if self.micros < 0 {
+                "-"
+            } else if after_negative {
+                "+"
+            } else {
+                ""
+            };
+            write!(f, "{}{}", if first { "" } else { " " }, sign)?;
+            write_clock(f, self.micros.unsigned_abs())?;
+        }
+        Ok(())
+    }
+}
+
+// takes postgres' own format, with units spelled out or abbreviated, an optional
+// hh:mm:ss part and a trailing `ago`, or iso 8601 like P1Y2M3DT4H5M6.5S
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) if rest.starts_with(['P', 'p']) => (true, rest),
+            _ => (false, s),
+        };
+        let mut parts = match unsigned.strip_prefix(['P', 'p']) {
+            Some(designators) => parse_iso(&designators.to_ascii_uppercase()),
+            None => parse_postgres(unsigned),
+        }
+        .ok_or_else(|| {
+            "expected an interval like 1 day 02:30:00, 3 mons ago or P1DT2H30M".to_string()
+        })?;
+        if negative {
+            parts.negate();
+        }
+        parts.interval()
+    }
+}
+
+const SECOND: i128 = MICROS_PER_SECOND as i128;
+const DAY: i128 = MICROS_PER_DAY as i128;
+
+enum Unit {
+    Micros(i128),
+    Days(i128),
+    Months(i128),
+}
+
+fn unit_named(name: &str) -> Option<Unit> {
+    Some(match name {
+        "microsecond" | "microseconds" | "us" | "usec" | "usecs" => Unit::Micros(1),
+        "millisecond" | "milliseconds" | "ms" | "msec" | "msecs" => Unit::Micros(1_000),
+        "second" | "seconds" | "s" | "sec" | "secs" => Unit::Micros(SECOND),
+        "minute" | "minutes" | "m" | "min" | "mins" => Unit::Micros(MICROS_PER_MINUTE as i128),
+        "hour" | "hours" | "h" | "hr" | "hrs" => Unit::Micros(MICROS_PER_HOUR as i128),
+        "day" | "days" | "d" => Unit::Days(1),
+        "week" | "weeks" | "w" => Unit::Days(7),
+        "mon" | "mons" | "month" | "months" => Unit::Months(1),
+        "year" | "years" | "y" | "yr" | "yrs" => Unit::Months(12),
+        "decade" | "decades" => Unit::Months(120),
+        "century" | "centuries" => Unit::Months(1_200),
+        "millennium" | "millennia" | "millenniums" => Unit::Months(12_000),
+        _ => return None,
+    })
+}
+
+// a decimal kept exact, as whole + fraction / scale
+struct Number {
+    negative: bool,
+    whole: i128,
+    fraction: i128,
+    scale: i128,
+}
+
+fn parse_number(s: &str) -> Option<Number> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() && fraction.is_empty()
+        || whole.len() > 20
+        || !all_digits(whole)
+        || !all_digits(fraction)
+    {
+        return None;
+    }
+    // digits past the 18th can't move the result by a microsecond
+    let fraction = &fraction[..fraction.len().min(18)];
+    Some(Number {
+        negative,
+        whole: if whole.is_empty() {
+            0
+        } else {
+            whole.parse().ok()?
+        },
+        fraction: if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse().ok()?
+        },
+        scale: 10i128.pow(fraction.len() as u32),
+    })
+}
+
+#[derive(Default)]
+struct Parts {
+    months: i128,
+    days: i128,
+    micros: i128,
+}
+
+impl Parts {
+    // fractions carry down the way postgres carries them: part of a day into
+    // time, part of a month into days of 30 and then time, and part of a year
+    // is rounded to whole months
+    fn add(&mut self, number: &Number, unit: Unit) {
+        let (whole, fraction, scale) = (number.whole, number.fraction, number.scale);
+        let mut parts = Parts::default();
+        match unit {
+            Unit::Micros(size) => parts.micros = whole * size + rounded(fraction * size, scale),
+            Unit::Days(size) => {
+                let days = fraction * size;
+                parts.days = whole * size + days / scale;
+                parts.micros = rounded(days % scale * DAY, scale);
+            }
+            Unit::Months(1) => {
+                parts.months = whole + fraction / scale;
+                let days = fraction % scale * 30;
+                parts.days = days / scale;
+                parts.micros = rounded(days % scale * DAY, scale);
+            }
+            Unit::Months(size) => parts.months = whole * size + rounded(fraction * size, scale),
+        }
+        if number.negative {
+            parts.negate();
+        }
+        self.months += parts.months;
+        self.days += parts.days;
+        self.micros += parts.micros;
+    }
+
+    fn negate(&mut self) {
+        self.months = -self.months;
+        self.days = -self.days;
+        self.micros = -self.micros;
+    }
+
+    fn interval(&self) -> Result<Interval, String> {
+        let out_of_range = |_| "interval out of range".to_string();
+        Ok(Interval {
+            months: i32::try_from(self.months).map_err(out_of_range)?,
+            days: i32::try_from(self.days).map_err(out_of_range)?,
+            micros: i64::try_from(self.micros).map_err(out_of_range)?,
+        })
+    }
+}
+
+fn rounded(numerator: i128, scale: i128) -> i128 {
+    (numerator * 2 + scale) / (scale * 2)
+}
+
+fn parse_postgres(s: &str) -> Option<Parts> {
+    let mut parts = Parts::default();
+    let mut pending: Option<Number> = None;
+    let mut empty = true;
+    let mut ago = false;
+    for token in s.split_whitespace() {
+        // `ago` has to come last
+        if ago {
+            return None;
+        }
+        let token = token.to_ascii_lowercase();
+        if token == "ago" {
+            ago = true;
+            continue;
+        }
+        empty = false;
+        if token.contains(':') {
+            if pending.is_some() {
+                return None;
+            }
+            let (negative, clock) = match token.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, token.strip_prefix('+').unwrap_or(&token)),
+            };
+            let micros = i128::from(parse_clock(clock)?);
+            parts.micros += if negative { -micros } else { micros };
+            continue;
+        }
+        let at = token
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(token.len());
+        let (number, unit) = token.split_at(at);
+        if !number.is_empty() {
+            if pending.is_some() {
+                return None;
+            }
+            pending = Some(parse_number(number)?);
+        }
+        if !unit.is_empty() {
+            parts.add(&pending.take()?, unit_named(unit)?);
+        }
+    }
+    // a bare number counts seconds
+    if let Some(number) = pending {
+        parts.add(&number, Unit::Micros(SECOND));
+    }
+    if empty {
+        return None;
+    }
+    if ago {
+        parts.negate();
+    }
+    Some(parts)
+}
+
+// what follows the P, uppercased
+fn parse_iso(s: &str) -> Option<Parts> {
+    let mut parts = Parts::default();
+    let mut time = false;
+    let mut empty = true;
+    let mut rest = s;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('T') {
+            if time {
+                return None;
+            }
+            time = true;
+            rest = after;
+            continue;
+        }
+        let at = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let number = parse_number(&rest[..at])?;
+        let unit = match (time, rest.as_bytes()[at]) {
+            (false, b'Y') => Unit::Months(12),
+            (false, b'M') => Unit::Months(1),
+            (false, b'W') => Unit::Days(7),
+            (false, b'D') => Unit::Days(1),
+            (true, b'H') => Unit::Micros(MICROS_PER_HOUR as i128),
+            (true, b'M') => Unit::Micros(MICROS_PER_MINUTE as i128),
+            (true, b'S') => Unit::Micros(SECOND),
+            _ => return None,
+        };
+        parts.add(&number, unit);
+        empty = false;
+        rest = &rest[at + 1..];
+    }
+    if empty {
+        return None;
+    }
+    Some(parts)
+}
+
+impl<'a> FromSql<'a> for Interval {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 16 {
+            return Err("invalid interval message".into());
+        }
+        let micros = raw.get_i64();
+        let days = raw.get_i32();
+        let months = raw.get_i32();
+        Ok(Interval {
+            months,
+            days,
+            micros,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+}
+
+impl ToSql for Interval {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_i64(self.micros);
+        out.put_i32(self.days);
+        out.put_i32(self.months);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_round_trip;
+
+    fn interval(months: i32, days: i32, micros: i64) -> Interval {
+        Interval {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_text_and_binary() {
+        for (value, text) in [
+            (interval(0, 0, 0), "00:00:00"),
+            (
+                interval(14, 3, 14_706_789_000),
+                "1 year 2 mons 3 days 04:05:06.789",
+            ),
+            (interval(-1, 3, 1_500_000), "-1 mons +3 days 00:00:01.5"),
+            (
+                interval(-14, -3, -1),
+                "-1 years -2 mons -3 days -00:00:00.000001",
+            ),
+            (interval(1, -1, 0), "1 mon -1 days"),
+            (interval(0, 1, -1), "1 day -00:00:00.000001"),
+            (interval(0, -1, 1), "-1 days +00:00:00.000001"),
+            (interval(0, 0, -5_400_000_000), "-01:30:00"),
+            (interval(0, 0, 360_000_000_000), "100:00:00"),
+            (interval(12, 0, 0), "1 year"),
+            (
+                interval(i32::MIN, i32::MIN, i64::MIN),
+                "-178956970 years -8 mons -2147483648 days -2562047788:00:54.775808",
+            ),
+        ] {
+            assert_eq!(value.to_string(), text);
+            assert_eq!(text.parse::<Interval>(), Ok(value), "{}", text);
+            assert_eq!(binary_round_trip(&value, &Type::INTERVAL), value);
+        }
+    }
+
+    #[test]
+    fn parses_units_fractions_and_ago() {
+        for (text, value) in [
+            ("1 day ago", interval(0, -1, 0)),
+            ("-1 mons 3 days -00:00:01.5 ago", interval(1, -3, 1_500_000)),
+            ("1.5 months", interval(1, 15, 0)),
+            ("1.5 years", interval(18, 0, 0)),
+            ("0.5 days", interval(0, 0, 43_200_000_000)),
+            ("1.5 weeks", interval(0, 10, 43_200_000_000)),
+            ("2h 30m", interval(0, 0, 9_000_000_000)),
+            ("3 DAYS 04:05", interval(0, 3, 14_700_000_000)),
+            ("90", interval(0, 0, 90_000_000)),
+            ("1.3 years", interval(16, 0, 0)),
+            ("0.0000016 seconds", interval(0, 0, 2)),
+            ("1 millisecond 1 us", interval(0, 0, 1_001)),
+            ("1 century", interval(1_200, 0, 0)),
+        ] {
+            assert_eq!(text.parse::<Interval>(), Ok(value), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_iso_8601() {
+        for (text, value) in [
+            ("P1Y2M3DT4H5M6.789S", interval(14, 3, 14_706_789_000)),
+            ("P-1M3DT-1.5S", interval(-1, 3, -1_500_000)),
+            ("-P1M3D", interval(-1, -3, 0)),
+            ("PT0.000001S", interval(0, 0, 1)),
+            ("P1W", interval(0, 7, 0)),
+            ("pt1m", interval(0, 0, 60_000_000)),
+        ] {
+            assert_eq!(text.parse::<Interval>(), Ok(value), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for text in [
+            "",
+            "ago",
+            "days",
+            "1 fortnight",
+            "1 day ago 2 hours",
+            "1 2 days",
+            "1:2:3:4",
+            "P",
+            "PT",
+            "P1H",
+            "P1DT2D",
+            "3000000000 days",
+        ] {
+            assert!(text.parse::<Interval>().is_err(), "{}", text);
+        }
+    }
+}
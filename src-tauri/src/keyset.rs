@@ -0,0 +1,339 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::filter::Params;
+use crate::sql::quote_ident;
+
+// key values are sent back through to_sql_value, so keyset pagination is only
+// offered when every key column has a type that survives that round trip
+const KEYSET_TYPES: &[&str] = &[
+    "bool",
+    "int2",
+    "int4",
+    "int8",
+    "float4",
+    "float8",
+    "numeric",
+    "text",
+    "varchar",
+    "name",
+    "uuid",
+    "date",
+    "timestamp",
+    "timestamptz",
+    "time",
+    "timetz",
+    "interval",
+];
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OrderKey {
+    pub column: String,
+    #[serde(skip)]
+    pg_type: String,
+    // what is sorted on: the quoted column, maybe with a collation, or an expression over it
+    #[serde(skip)]
+    expression: String,
+    // whether the sorted value is the column's value in the row, which cursors are built from
+    #[serde(skip)]
+    from_row: bool,
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl OrderKey {
+    pub fn column(column: &str, pg_type: &str) -> Self {
+        OrderKey::expression(column, pg_type, quote_ident(column), true)
+    }
+
+    pub fn expression(column: &str, pg_type: &str, expression: String, from_row: bool) -> Self {
+        OrderKey {
+            column: column.to_string(),
+            pg_type: pg_type.to_string(),
+            expression,
+            from_row,
+            descending: false,
+            nulls_first: false,
+        }
+    }
+
+    // without an explicit choice postgres sorts nulls as if they were larger than every value
+    pub fn direction(self, descending: bool, nulls_first: Option<bool>) -> Self {
+        OrderKey {
+            descending,
+            nulls_first: nulls_first.unwrap_or(descending),
+            ..self
+        }
+    }
+
+    pub fn reads_column(&self, column: &str) -> bool {
+        self.from_row && self.column == column
+    }
+
+    fn reversed(&self) -> Self {
+        OrderKey {
+            descending: !self.descending,
+            nulls_first: !self.nulls_first,
+            ..self.clone()
+        }
+    }
+
+    pub fn sql(&self) -> String {
+        format!(
+            "{} {} NULLS {}",
+            self.expression,
+            if self.descending { "DESC" } else { "ASC" },
+            if self.nulls_first { "FIRST" } else { "LAST" }
+        )
+    }
+
+    // rows strictly after `value` in this key's order
+    fn after(&self, value: &Value, params: &mut Params) -> Result<String, Error> {
+        let column = &self.expression;
+        if value.is_null() {
+            return Ok(if self.nulls_first {
+                format!("{} IS NOT NULL", column)
+            } else {
+                "FALSE".to_string()
+            });
+        }
+        let placeholder = self.bind(value, params)?;
+        let operator = if self.descending { "<" } else { ">" };
+        Ok(if self.nulls_first {
+            format!("{} {} {}", column, operator, placeholder)
+        } else {
+            format!(
+                "({} {} {} OR {} IS NULL)",
+                column, operator, placeholder, column
+            )
+        })
+    }
+
+    fn equal(&self, value: &Value, params: &mut Params) -> Result<String, Error> {
+        let column = &self.expression;
+        if value.is_null() {
+            return Ok(format!("{} IS NULL", column));
+        }
+        Ok(format!("{} = {}", column, self.bind(value, params)?))
+    }
+
+    fn bind(&self, value: &Value, params: &mut Params) -> Result<String, Error> {
+        let param =
+            crate::to_sql_value_literal(value, &self.pg_type).map_err(|_| invalid_cursor())?;
+        params.push(param);
+        Ok(format!("${}", params.len()))
+    }
+}
+
+pub fn supported(order: &[OrderKey]) -> bool {
+    !order.is_empty()
+        && order
+            .iter()
+            .all(|key| key.from_row && KEYSET_TYPES.contains(&key.pg_type.as_str()))
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Next,
+    Prev,
+}
+
+// what the ui gets back as an opaque string; `order` pins the cursor to the
+// ordering it was made for so it can't be replayed against another one
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Cursor {
+    pub direction: Direction,
+    order: Vec<(String, bool, bool)>,
+    values: Vec<Value>,
+}
+
+impl Cursor {
+    pub fn new(direction: Direction, order: &[OrderKey], values: Vec<Value>) -> Self {
+        Cursor {
+            direction,
+            order: describe(order),
+            values,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        BASE64.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str, order: &[OrderKey]) -> Result<Self, Error> {
+        let cursor: Cursor = BASE64
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(invalid_cursor)?;
+        if cursor.order != describe(order) || cursor.values.len() != order.len() {
+            return Err(Error::validation(
+                "cursor was made for a different sort order; reload the first page",
+            ));
+        }
+        Ok(cursor)
+    }
+
+    // the order rows have to be read in; previous pages are read backwards and
+    // flipped afterwards
+    pub fn read_order(&self, order: &[OrderKey]) -> Vec<OrderKey> {
+        match self.direction {
+            Direction::Next => order.to_vec(),
+            Direction::Prev => order.iter().map(OrderKey::reversed).collect(),
+        }
+    }
+
+    // rows after the cursor in `read_order`: for keys k1..kn that is
+    // k1 after v1, or k1 = v1 and k2 after v2, and so on
+    pub fn condition(&self, read_order: &[OrderKey], params: &mut Params) -> Result<String, Error> {
+        let mut alternatives = Vec::with_capacity(read_order.len());
+        for (i, key) in read_order.iter().enumerate() {
+            let mut terms = Vec::with_capacity(i + 1);
+            for (prefix, value) in read_order[..i].iter().zip(&self.values) {
+                terms.push(prefix.equal(value, params)?);
+            }
+            terms.push(key.after(&self.values[i], params)?);
+            alternatives.push(format!("({})", terms.join(" AND ")));
+        }
+        Ok(format!("({})", alternatives.join(" OR ")))
+    }
+}
+
+fn describe(order: &[OrderKey]) -> Vec<(String, bool, bool)> {
+    order
+        .iter()
+        .map(|key| (key.expression.clone(), key.descending, key.nulls_first))
+        .collect()
+}
+
+fn invalid_cursor() -> Error {
+    Error::validation("invalid cursor")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use serde_json::json;
+    use tokio_postgres::types::Type;
+
+    // a nullable name sorted descending, then the primary key as the tiebreaker
+    fn order() -> Vec<OrderKey> {
+        vec![
+            OrderKey::column("name", "text").direction(true, None),
+            OrderKey::column("id", "int4").direction(false, None),
+        ]
+    }
+
+    fn encoded(params: &Params, types: &[Type]) -> Vec<Vec<u8>> {
+        params
+            .iter()
+            .zip(types)
+            .map(|(param, ty)| {
+                let mut out = BytesMut::new();
+                param.to_sql_checked(ty, &mut out).unwrap();
+                out.to_vec()
+            })
+            .collect()
+    }
+
+    fn condition(direction: Direction, values: Vec<Value>) -> (String, Params) {
+        let cursor = Cursor::new(direction, &order(), values);
+        let cursor = Cursor::decode(&cursor.encode(), &order()).unwrap();
+        let mut params = Params::new();
+        let sql = cursor
+            .condition(&cursor.read_order(&order()), &mut params)
+            .unwrap();
+        (sql, params)
+    }
+
+    #[test]
+    fn next_pages_continue_in_the_sort_order() {
+        let (sql, params) = condition(Direction::Next, vec![json!("b"), json!(7)]);
+        assert_eq!(
+            sql,
+            r#"(("name" < $1) OR ("name" = $2 AND ("id" > $3 OR "id" IS NULL)))"#
+        );
+        assert_eq!(
+            encoded(&params, &[Type::TEXT, Type::TEXT, Type::INT4]),
+            [b"b".to_vec(), b"b".to_vec(), 7i32.to_be_bytes().to_vec()]
+        );
+    }
+
+    #[test]
+    fn previous_pages_read_the_order_backwards() {
+        let reversed: Vec<String> = Cursor::new(Direction::Prev, &order(), vec![])
+            .read_order(&order())
+            .iter()
+            .map(OrderKey::sql)
+            .collect();
+        assert_eq!(
+            reversed,
+            [r#""name" ASC NULLS LAST"#, r#""id" DESC NULLS FIRST"#]
+        );
+
+        let (sql, params) = condition(Direction::Prev, vec![json!("b"), json!(7)]);
+        assert_eq!(
+            sql,
+            r#"((("name" > $1 OR "name" IS NULL)) OR ("name" = $2 AND "id" < $3))"#
+        );
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn null_keys_compare_by_where_nulls_sort() {
+        // descending sorts nulls first, so the rows after a null name are the named ones
+        let (sql, params) = condition(Direction::Next, vec![Value::Null, json!(7)]);
+        assert_eq!(
+            sql,
+            r#"(("name" IS NOT NULL) OR ("name" IS NULL AND ("id" > $1 OR "id" IS NULL)))"#
+        );
+        assert_eq!(
+            encoded(&params, &[Type::INT4]),
+            [7i32.to_be_bytes().to_vec()]
+        );
+
+        // read backwards nulls come last, and nothing follows them
+        let (sql, params) = condition(Direction::Prev, vec![Value::Null, json!(7)]);
+        assert_eq!(sql, r#"((FALSE) OR ("name" IS NULL AND "id" < $1))"#);
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn rejects_cursors_made_for_another_order() {
+        let encoded = Cursor::new(Direction::Next, &order(), vec![json!("b"), json!(7)]).encode();
+
+        let flipped = vec![
+            OrderKey::column("name", "text").direction(false, None),
+            OrderKey::column("id", "int4").direction(false, None),
+        ];
+        let nulls_moved = vec![
+            OrderKey::column("name", "text").direction(true, Some(false)),
+            OrderKey::column("id", "int4").direction(false, None),
+        ];
+        let fewer_keys = vec![OrderKey::column("id", "int4")];
+        for other in [flipped, nulls_moved, fewer_keys] {
+            let err = Cursor::decode(&encoded, &other).unwrap_err();
+            assert!(err.message().contains("different sort order"), "{:?}", err);
+        }
+
+        let err = Cursor::decode("not a cursor", &order()).unwrap_err();
+        assert_eq!(err.message(), "invalid cursor");
+    }
+
+    #[test]
+    fn only_row_values_of_round_tripping_types_make_cursors() {
+        assert!(supported(&order()));
+        assert!(!supported(&[]));
+        assert!(!supported(&[OrderKey::column("doc", "jsonb")]));
+        assert!(!supported(&[OrderKey::expression(
+            "doc",
+            "jsonb",
+            r#""doc" #>> $1"#.to_string(),
+            false
+        )]));
+    }
+}
@@ -1,21 +1,73 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use bytes::BytesMut;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use pgvector::Vector;
-use rustls::SignatureScheme;
 use serde_json::Value;
-use std::str::FromStr;
-use std::sync::Arc;
-use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
-use tokio_postgres::types::{ToSql, Type};
-use tokio_postgres::Row;
-use tokio_postgres_rustls::MakeRustlsConnect;
+use tauri::{Manager, State, WebviewUrl, WebviewWindowBuilder};
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type};
 use uuid::Uuid;
 
+mod array;
+mod cast;
+mod cell;
+mod clock;
+mod connstr;
+mod count;
+mod error;
+mod filter;
+mod interval;
+mod keyset;
+mod numeric;
+mod pgenv;
+mod pool;
+mod profile;
+mod sort;
+mod sql;
+mod timeouts;
+mod tls;
+mod tunnel;
+mod vault;
+
+use array::ArrayParam;
+use cast::ParamCasts;
+use cell::Cell;
+use clock::{Time, TimeTz};
+use count::RowCount;
+use error::Error;
+use filter::{FilterGroup, Params};
+use interval::Interval;
+use keyset::{Cursor, Direction, OrderKey};
+use numeric::Numeric;
+use pgenv::ServiceEntry;
+use pool::{ConnectionRegistry, SessionId, SessionInfo};
+use profile::ConnectionProfile;
+use sort::Sort;
+use sql::{qualified_name, quote_ident, KnownColumns};
+use timeouts::Timeouts;
+use vault::{ProfileSummary, Vault, VaultStatus};
+
+// rows the grid can ask for at once
+const MAX_PAGE_SIZE: i64 = 10_000;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_os::init())
+        .manage(ConnectionRegistry::default())
         .invoke_handler(tauri::generate_handler![
+            connect,
+            disconnect,
+            list_sessions,
+            list_services,
+            vault_status,
+            unlock_vault,
+            lock_vault,
+            rekey_vault,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            connect_profile,
+            cancel_query,
             list_schemas,
             list_tables_by_schema,
             get_table_data,
@@ -25,6 +77,11 @@ pub fn run() {
             get_table_column_types
         ])
         .setup(|app| {
+            app.state::<ConnectionRegistry>().spawn_reaper();
+            app.manage(Vault::new(
+                app.path().app_data_dir()?.join("connections.vault"),
+            ));
+
             if app.get_webview_window("main").is_some() {
                 return Ok(());
             }
@@ -39,56 +96,6 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
-#[derive(Debug)]
-struct NoVerification;
-
-impl rustls::client::danger::ServerCertVerifier for NoVerification {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &rustls::pki_types::CertificateDer<'_>,
-        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
-        _server_name: &rustls::pki_types::ServerName<'_>,
-        _ocsp_response: &[u8],
-        _now: rustls::pki_types::UnixTime,
-    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::danger::ServerCertVerified::assertion())
-    }
-
-    fn verify_tls12_signature(
-        &self,
-        _message: &[u8],
-        _cert: &rustls::pki_types::CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn verify_tls13_signature(
-        &self,
-        _message: &[u8],
-        _cert: &rustls::pki_types::CertificateDer<'_>,
-        _dss: &rustls::DigitallySignedStruct,
-    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
-        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
-    }
-
-    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
-        vec![
-            SignatureScheme::RSA_PSS_SHA256,
-            SignatureScheme::RSA_PSS_SHA384,
-            SignatureScheme::RSA_PSS_SHA512,
-            SignatureScheme::RSA_PKCS1_SHA256,
-            SignatureScheme::RSA_PKCS1_SHA384,
-            SignatureScheme::RSA_PKCS1_SHA512,
-            SignatureScheme::ECDSA_NISTP256_SHA256,
-            SignatureScheme::ECDSA_NISTP384_SHA384,
-            SignatureScheme::ECDSA_NISTP521_SHA512,
-            SignatureScheme::ED25519,
-            SignatureScheme::ED448,
-        ]
-    }
-}
-
 #[derive(serde::Serialize, Debug)]
 pub struct Table {
     pub schema: String,
@@ -98,7 +105,15 @@ pub struct Table {
 #[derive(serde::Serialize, Debug)]
 pub struct TableData {
     pub columns: Vec<ColumnInfo>,
-    pub rows: Vec<Vec<Value>>,
+    pub rows: Vec<Vec<Cell>>,
+    // rows matching the filters across all pages
+    pub row_count: RowCount,
+    // opaque keyset cursors for the neighbouring pages; None when there is no
+    // such page or the table has no primary key to page by
+    pub prev_cursor: Option<String>,
+    pub next_cursor: Option<String>,
+    // the ORDER BY the rows were read with, sorts first and then the tiebreakers
+    pub order_by: Vec<OrderKey>,
 }
 
 #[derive(serde::Serialize, Debug)]
@@ -106,159 +121,282 @@ pub struct ColumnInfo {
     pub name: String,
     pub pg_type: String,
     pub is_nullable: bool,
+    // selected through a ::text cast because zubr doesn't decode the type itself
+    pub as_text: bool,
 }
 
 #[derive(serde::Deserialize, Debug)]
-pub struct Filter {
-    column: String,
-    operator: String,
-    value: String,
+struct UpdateRow {
+    pks: serde_json::Value,
+    changes: serde_json::Value,
 }
 
-#[derive(serde::Deserialize, Debug)]
-pub struct Sort {
-    column: String,
-    direction: String,
+// binds NULL whatever the parameter's type is
+#[derive(Debug)]
+struct Null;
+
+impl ToSql for Null {
+    fn to_sql(
+        &self,
+        _: &Type,
+        _: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(IsNull::Yes)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
 }
 
-#[derive(serde::Deserialize, Debug)]
-struct UpdateRow {
-    pks: serde_json::Value,
-    changes: serde_json::Value,
+// an empty string is NULL to to_sql_value, which is what a cleared edit field
+// means; filter values, cursor keys and array elements that are the empty string
+// are bound through here instead
+fn to_sql_value_literal(
+    value: &Value,
+    pg_type_name: &str,
+) -> Result<Box<dyn ToSql + Send + Sync>, String> {
+    match value {
+        Value::String(s) if s.is_empty() => Ok(Box::new(String::new())),
+        _ => to_sql_value(value, pg_type_name),
+    }
 }
 
+// converts a cell or filter value into a parameter of the column's type, so
+// tokio-postgres' type check accepts it for every column kind the grid can show
 fn to_sql_value(value: &Value, pg_type_name: &str) -> Result<Box<dyn ToSql + Send + Sync>, String> {
-    if value.is_null() {
-        return Ok(Box::new(None::<i32>));
+    match value {
+        Value::Null => return Ok(Box::new(Null)),
+        Value::String(s) if s.is_empty() => return Ok(Box::new(Null)),
+        _ => {}
     }
 
-    if pg_type_name == "bool" {
-        return match value.as_bool() {
-            Some(b) => Ok(Box::new(b)),
-            None => Err("expected boolean for bool type".to_string()),
+    // udt names of array types are the member's with a leading underscore, like _int4
+    if let Some(member_type) = pg_type_name.strip_prefix('_') {
+        let parsed;
+        let value = match value {
+            Value::String(s) => {
+                parsed = serde_json::from_str(s).map_err(|e| format!("invalid array: {}", e))?;
+                &parsed
+            }
+            _ => value,
         };
+        return Ok(Box::new(ArrayParam::from_json(value, member_type)?));
     }
 
-    if let Some(s) = value.as_str() {
-        if s.is_empty() {
-            return Ok(Box::new(None::<String>));
+    match pg_type_name {
+        "json" | "jsonb" => {
+            return match value {
+                Value::String(s) => serde_json::from_str::<Value>(s)
+                    .map(|v| Box::new(v) as Box<dyn ToSql + Send + Sync>)
+                    .map_err(|e| format!("invalid json: {}", e)),
+                _ => Ok(Box::new(value.clone())),
+            };
         }
-
-        match pg_type_name {
-            "timestamp" | "timestamptz" => {
-                let parsed = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
-                    .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
-                    .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
-                    .map_err(|e| format!("invalid timestamp format: {}", e))?;
-                Ok(Box::new(parsed))
-            }
-            "date" => {
-                let parsed = NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                    .map_err(|e| format!("invalid date format: {}", e))?;
-                Ok(Box::new(parsed))
-            }
-            "uuid" => {
-                let parsed =
-                    Uuid::parse_str(s).map_err(|e| format!("invalid uuid format: {}", e))?;
-                Ok(Box::new(parsed))
+        "vector" => {
+            let parsed: Vec<f32> = match value {
+                Value::String(s) => serde_json::from_str(s),
+                _ => serde_json::from_value(value.clone()),
             }
-            "int2" | "int4" | "int8" => s
-                .parse::<i64>()
-                .map(|v| Box::new(v) as Box<dyn ToSql + Send + Sync>)
-                .map_err(|e| format!("invalid integer format: {}", e)),
-            "float4" | "float8" => s
-                .parse::<f64>()
-                .map(|v| Box::new(v) as Box<dyn ToSql + Send + Sync>)
-                .map_err(|e| format!("invalid float format: {}", e)),
-            _ => Ok(Box::new(s.to_string())),
+            .map_err(|e| format!("invalid vector format: {}", e))?;
+            return Ok(Box::new(Vector::from(parsed)));
         }
-    } else if let Some(n) = value.as_i64() {
-        Ok(Box::new(n))
-    } else if let Some(f) = value.as_f64() {
-        Ok(Box::new(f))
-    } else if let Some(b) = value.as_bool() {
-        Ok(Box::new(b))
-    } else {
-        Err(format!("unsupported value type for: {:?}", value))
+        _ => {}
+    }
+
+    let s = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => return Err(format!("unsupported value type for: {:?}", value)),
+    };
+
+    match pg_type_name {
+        "bool" => parse_as::<bool>(&s, "boolean"),
+        "int2" => parse_as::<i16>(&s, "integer"),
+        "int4" => parse_as::<i32>(&s, "integer"),
+        "int8" => parse_as::<i64>(&s, "integer"),
+        "float4" => parse_as::<f32>(&s, "float"),
+        "float8" => parse_as::<f64>(&s, "float"),
+        "numeric" => parse_as::<Numeric>(&s, "numeric"),
+        "timestamp" => Ok(Box::new(parse_timestamp(&s)?)),
+        "timestamptz" => Ok(Box::new(parse_timestamptz(&s)?)),
+        "time" => parse_as::<Time>(&s, "time"),
+        "timetz" => parse_as::<TimeTz>(&s, "time with time zone"),
+        "interval" => parse_as::<Interval>(&s, "interval"),
+        "date" => {
+            let parsed = NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map_err(|e| format!("invalid date format: {}", e))?;
+            Ok(Box::new(parsed))
+        }
+        "uuid" => {
+            let parsed = Uuid::parse_str(&s).map_err(|e| format!("invalid uuid format: {}", e))?;
+            Ok(Box::new(parsed))
+        }
+        _ => Ok(Box::new(s)),
     }
 }
 
+fn parse_as<T>(s: &str, kind: &str) -> Result<Box<dyn ToSql + Send + Sync>, String>
+where
+    T: std::str::FromStr + ToSql + Send + Sync + 'static,
+    T::Err: std::fmt::Display,
+{
+    s.trim()
+        .parse::<T>()
+        .map(|v| Box::new(v) as Box<dyn ToSql + Send + Sync>)
+        .map_err(|e| format!("invalid {} format: {}", kind, e))
+}
+
+fn parse_timestamp(s: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN)))
+        .map_err(|e| format!("invalid timestamp format: {}", e))
+}
+
+// accepts an explicit offset, otherwise reads the value as utc like the grid shows it
+fn parse_timestamptz(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%#z"))
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| parse_timestamp(s.trim_end_matches(" UTC")).map(|dt| dt.and_utc()))
+}
+
 #[tauri::command]
-async fn get_primary_keys(uri: &str, schema: &str, table: &str) -> Result<Vec<String>, String> {
-    let config = tokio_postgres::Config::from_str(uri).map_err(|e| e.to_string())?;
+async fn connect(
+    registry: State<'_, ConnectionRegistry>,
+    profile: ConnectionProfile,
+) -> Result<SessionId, Error> {
+    registry.connect(&profile).await
+}
 
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
+#[tauri::command]
+fn disconnect(registry: State<'_, ConnectionRegistry>, session_id: &str) -> Result<(), Error> {
+    registry.disconnect(session_id)
+}
 
-    let (client, connection) = config.connect(tls).await.map_err(|e| e.to_string())?;
+#[tauri::command]
+fn list_sessions(registry: State<'_, ConnectionRegistry>) -> Vec<SessionInfo> {
+    registry.list()
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+#[tauri::command]
+fn list_services() -> Result<Vec<ServiceEntry>, Error> {
+    pgenv::list_services().map_err(Error::validation)
+}
 
-    let query = "
-        SELECT kcu.column_name
-        FROM information_schema.key_column_usage AS kcu
-        JOIN information_schema.table_constraints AS tc
-        ON kcu.constraint_name = tc.constraint_name AND kcu.table_schema = tc.table_schema
-        WHERE tc.constraint_type = 'PRIMARY KEY'
-        AND kcu.table_schema = $1
-        AND kcu.table_name = $2;
-    ";
+#[tauri::command]
+fn vault_status(vault: State<'_, Vault>) -> VaultStatus {
+    vault.status()
+}
 
-    let rows = client
-        .query(query, &[&schema, &table])
-        .await
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+fn unlock_vault(vault: State<'_, Vault>, password: &str) -> Result<(), Error> {
+    vault.unlock(password)
+}
 
-    let pks = rows.iter().map(|row| row.get("column_name")).collect();
+#[tauri::command]
+fn lock_vault(vault: State<'_, Vault>) {
+    vault.lock()
+}
 
-    Ok(pks)
+#[tauri::command]
+fn rekey_vault(
+    vault: State<'_, Vault>,
+    current_password: &str,
+    new_password: &str,
+) -> Result<(), Error> {
+    vault.rekey(current_password, new_password)
+}
+
+#[tauri::command]
+fn list_profiles(vault: State<'_, Vault>) -> Result<Vec<ProfileSummary>, Error> {
+    vault.list()
+}
+
+#[tauri::command]
+fn save_profile(
+    vault: State<'_, Vault>,
+    name: &str,
+    profile: ConnectionProfile,
+) -> Result<(), Error> {
+    vault.save(name, profile)
+}
+
+#[tauri::command]
+fn delete_profile(vault: State<'_, Vault>, name: &str) -> Result<(), Error> {
+    vault.delete(name)
+}
+
+#[tauri::command]
+async fn connect_profile(
+    vault: State<'_, Vault>,
+    registry: State<'_, ConnectionRegistry>,
+    name: &str,
+) -> Result<SessionId, Error> {
+    let profile = vault.profile(name)?;
+    registry.connect(&profile).await
+}
+
+#[tauri::command]
+async fn cancel_query(
+    registry: State<'_, ConnectionRegistry>,
+    request_id: &str,
+) -> Result<(), Error> {
+    registry.cancel(request_id).await
+}
+
+#[tauri::command]
+async fn get_primary_keys(
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, Error> {
+    let client = registry.get(session_id).await?;
+    query_primary_keys(&client, schema, table).await
 }
 
 #[tauri::command]
 async fn update_rows(
-    uri: &str,
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
     schema: &str,
     table: &str,
     changes: Vec<UpdateRow>,
-) -> Result<(), String> {
-    let config = tokio_postgres::Config::from_str(uri).map_err(|e| e.to_string())?;
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
-    let (mut client, connection) = config.connect(tls).await.map_err(|e| e.to_string())?;
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let column_type_tuples = get_table_column_types(uri, schema, table).await?;
-    let column_types: std::collections::HashMap<String, String> =
-        column_type_tuples.into_iter().collect();
+    timeouts: Option<Timeouts>,
+) -> Result<(), Error> {
+    let mut client = registry.get_writable(session_id).await?;
+
+    let column_types = query_column_types(&client, schema, table).await?;
+    let known = KnownColumns::new(
+        column_types
+            .iter()
+            .map(|(name, pg_type)| (name.as_str(), pg_type.as_str())),
+    );
+    let casts = ParamCasts::load(&client, schema, table, &known).await?;
 
-    let transaction = client.transaction().await.map_err(|e| e.to_string())?;
+    let transaction = client.transaction().await?;
+    if let Some(timeouts) = timeouts {
+        timeouts.set_local(&transaction).await?;
+    }
 
-    for change in changes {
+    for (row, change) in changes.iter().enumerate() {
         let pks = if let Some(obj) = change.pks.as_object() {
             obj
         } else {
-            return Err("pks is not an object".to_string());
+            return Err(Error::validation("pks is not an object").at(row, None));
         };
 
         let updates = if let Some(obj) = change.changes.as_object() {
             obj
         } else {
-            return Err("changes is not an object".to_string());
+            return Err(Error::validation("changes is not an object").at(row, None));
         };
 
         if updates.is_empty() {
@@ -271,27 +409,38 @@ async fn update_rows(
         let mut param_count = 1;
 
         for (key, value) in updates.iter() {
-            set_clauses.push(format!("\"{}\" = ${}", key, param_count));
-            let col_type = column_types
-                .get(key)
-                .ok_or_else(|| format!("column type not found for {}", key))?;
-            params.push(to_sql_value(value, col_type)?);
+            let col_type = known.type_of(key).map_err(|e| e.at(row, None))?;
+            params.push(
+                casts
+                    .param(key, col_type, value)
+                    .map_err(|e| Error::validation(e).at(row, Some(key)))?,
+            );
+            set_clauses.push(format!(
+                "{} = {}",
+                quote_ident(key),
+                casts.placeholder(key, param_count)
+            ));
             param_count += 1;
         }
 
         for (key, value) in pks.iter() {
-            where_clauses.push(format!("\"{}\" = ${}", key, param_count));
-            let col_type = column_types
-                .get(key)
-                .ok_or_else(|| format!("column type not found for {}", key))?;
-            params.push(to_sql_value(value, col_type)?);
+            let col_type = known.type_of(key).map_err(|e| e.at(row, None))?;
+            params.push(
+                casts
+                    .param(key, col_type, value)
+                    .map_err(|e| Error::validation(e).at(row, Some(key)))?,
+            );
+            where_clauses.push(format!(
+                "{} = {}",
+                quote_ident(key),
+                casts.placeholder(key, param_count)
+            ));
             param_count += 1;
         }
 
         let query = format!(
-            "UPDATE \"{}\".\"{}\" SET {} WHERE {}",
-            schema,
-            table,
+            "UPDATE {} SET {} WHERE {}",
+            qualified_name(schema, table),
             set_clauses.join(", "),
             where_clauses.join(" AND ")
         );
@@ -304,37 +453,24 @@ async fn update_rows(
         transaction
             .execute(query.as_str(), &params_slice)
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| {
+                // a single edited cell is the only one the failure can point at
+                let column = updates.keys().next().filter(|_| updates.len() == 1);
+                Error::from(e).at(row, column.map(String::as_str))
+            })?;
     }
 
-    transaction.commit().await.map_err(|e| e.to_string())?;
+    transaction.commit().await?;
 
     Ok(())
 }
 
 #[tauri::command]
-async fn list_schemas(uri: &str) -> Result<Vec<String>, String> {
-    let config = match tokio_postgres::Config::from_str(uri) {
-        Ok(config) => config,
-        Err(e) => return Err(e.to_string()),
-    };
-
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
-
-    let (client, connection) = match config.connect(tls).await {
-        Ok((client, connection)) => (client, connection),
-        Err(e) => return Err(e.to_string()),
-    };
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+async fn list_schemas(
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
+) -> Result<Vec<String>, Error> {
+    let client = registry.get(session_id).await?;
 
     let rows = match client
         .query(
@@ -344,7 +480,7 @@ async fn list_schemas(uri: &str) -> Result<Vec<String>, String> {
         .await
     {
         Ok(rows) => rows,
-        Err(e) => return Err(e.to_string()),
+        Err(e) => return Err(e.into()),
     };
 
     let mut schemas = Vec::new();
@@ -356,28 +492,12 @@ async fn list_schemas(uri: &str) -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-async fn list_tables_by_schema(uri: &str, schema: &str) -> Result<Vec<String>, String> {
-    let config = match tokio_postgres::Config::from_str(uri) {
-        Ok(config) => config,
-        Err(e) => return Err(e.to_string()),
-    };
-
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
-
-    let (client, connection) = match config.connect(tls).await {
-        Ok((client, connection)) => (client, connection),
-        Err(e) => return Err(e.to_string()),
-    };
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+async fn list_tables_by_schema(
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
+    schema: &str,
+) -> Result<Vec<String>, Error> {
+    let client = registry.get(session_id).await?;
 
     let rows = match client
         .query(
@@ -387,7 +507,7 @@ async fn list_tables_by_schema(uri: &str, schema: &str) -> Result<Vec<String>, S
         .await
     {
         Ok(rows) => rows,
-        Err(e) => return Err(e.to_string()),
+        Err(e) => return Err(e.into()),
     };
 
     let mut tables = Vec::new();
@@ -399,28 +519,13 @@ async fn list_tables_by_schema(uri: &str, schema: &str) -> Result<Vec<String>, S
 }
 
 #[tauri::command]
-async fn get_table_columns(uri: &str, schema: &str, table: &str) -> Result<Vec<String>, String> {
-    let config = match tokio_postgres::Config::from_str(uri) {
-        Ok(config) => config,
-        Err(e) => return Err(e.to_string()),
-    };
-
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
-
-    let (client, connection) = match config.connect(tls).await {
-        Ok((client, connection)) => (client, connection),
-        Err(e) => return Err(e.to_string()),
-    };
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+async fn get_table_columns(
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, Error> {
+    let client = registry.get(session_id).await?;
 
     let rows = match client
         .query(
@@ -430,7 +535,7 @@ async fn get_table_columns(uri: &str, schema: &str, table: &str) -> Result<Vec<S
         .await
     {
         Ok(rows) => rows,
-        Err(e) => return Err(e.to_string()),
+        Err(e) => return Err(e.into()),
     };
 
     let mut columns = Vec::new();
@@ -442,109 +547,134 @@ async fn get_table_columns(uri: &str, schema: &str, table: &str) -> Result<Vec<S
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn get_table_data(
-    uri: &str,
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
     schema: &str,
     table: &str,
     limit: i64,
     offset: i64,
-    filters: Option<Vec<Filter>>,
-    logical_operator: Option<String>,
+    cursor: Option<String>,
+    filters: Option<FilterGroup>,
     sorts: Option<Vec<Sort>>,
-) -> Result<TableData, String> {
-    let config = match tokio_postgres::Config::from_str(uri) {
-        Ok(config) => config,
-        Err(e) => return Err(e.to_string())?,
-    };
-
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
-
-    let (client, connection) = match config.connect(tls).await {
-        Ok((client, connection)) => (client, connection),
-        Err(e) => return Err(e.to_string())?,
-    };
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+    request_id: Option<String>,
+    timeouts: Option<Timeouts>,
+) -> Result<TableData, Error> {
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(Error::validation(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+    let request = registry.track(session_id, request_id.as_deref());
+    let mut client = registry.get(session_id).await?;
 
     let column_query = "SELECT column_name, udt_name, is_nullable FROM information_schema.columns WHERE table_schema = $1 AND table_name = $2 ORDER BY ordinal_position";
-    let column_rows = client
-        .query(column_query, &[&schema, &table])
-        .await
-        .map_err(|e| e.to_string())?;
+    let column_rows = client.query(column_query, &[&schema, &table]).await?;
 
     let columns: Vec<ColumnInfo> = column_rows
         .iter()
         .map(|row| {
             let is_nullable_str: &str = row.get("is_nullable");
+            let pg_type: String = row.get("udt_name");
             ColumnInfo {
                 name: row.get("column_name"),
-                pg_type: row.get("udt_name"),
+                as_text: !cell::is_native(&pg_type),
+                pg_type,
                 is_nullable: is_nullable_str == "YES",
             }
         })
         .collect();
 
-    let mut params: Vec<Box<dyn ToSql + Send + Sync>> = Vec::new();
-    let mut param_id = 1;
+    let known = KnownColumns::new(
+        columns
+            .iter()
+            .map(|column| (column.name.as_str(), column.pg_type.as_str())),
+    );
+    let mut params: Params = Vec::new();
+    let casts = match &filters {
+        Some(_) => ParamCasts::load(&client, schema, table, &known).await?,
+        None => ParamCasts::default(),
+    };
+    let filter_condition = filter::condition(filters.as_ref(), &known, &casts, &mut params)?;
+    let filter_param_count = params.len();
+
+    let mut order = sort::order_keys(
+        sorts.as_deref().unwrap_or_default(),
+        &client,
+        &known,
+        &mut params,
+    )
+    .await?;
+    // the primary key breaks ties, so every row has a fixed position to page from;
+    // without one the physical location keeps pages from overlapping, though an
+    // update moves the row it touches
+    let primary_keys = query_primary_keys(&client, schema, table).await?;
+    for pk in &primary_keys {
+        if !order.iter().any(|key| key.reads_column(pk)) {
+            order.push(OrderKey::column(pk, known.type_of(pk)?));
+        }
+    }
+    if primary_keys.is_empty() {
+        order.push(OrderKey::column("ctid", "tid"));
+    }
+    let keyset_supported = !primary_keys.is_empty() && keyset::supported(&order);
 
-    let where_clause = if let Some(filters) = filters {
-        if !filters.is_empty() {
-            let operator = logical_operator.unwrap_or_else(|| "AND".to_string());
-            let conditions: Vec<String> = filters
-                .iter()
-                .map(|f| {
-                    let condition = format!("\"{}\" {} ${}", f.column, f.operator, param_id);
-                    params.push(Box::new(f.value.clone()));
-                    param_id += 1;
-                    condition
-                })
-                .collect();
-            format!("WHERE {}", conditions.join(&format!(" {} ", operator)))
-        } else {
-            "".to_string()
+    let cursor = match cursor.as_deref() {
+        Some(_) if !keyset_supported => {
+            return Err(Error::validation(
+                "cursor pagination needs a primary key and sortable key columns",
+            ))
         }
-    } else {
-        "".to_string()
+        Some(cursor) => Some(Cursor::decode(cursor, &order)?),
+        None => None,
+    };
+    let read_order = match &cursor {
+        Some(cursor) => cursor.read_order(&order),
+        None => order.clone(),
     };
 
-    let order_by_clause = if let Some(sorts) = sorts {
-        if !sorts.is_empty() {
-            let sort_conditions: Vec<String> = sorts
-                .iter()
-                .filter_map(|s| {
-                    let direction = s.direction.to_uppercase();
-                    if direction == "ASC" || direction == "DESC" {
-                        Some(format!("\"{}\" {}", s.column, direction))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            if !sort_conditions.is_empty() {
-                format!("ORDER BY {}", sort_conditions.join(", "))
+    let count_from = match &filter_condition {
+        Some(condition) => format!("FROM {} WHERE {}", qualified_name(schema, table), condition),
+        None => format!("FROM {}", qualified_name(schema, table)),
+    };
+    let page_from = match &cursor {
+        Some(cursor) => format!(
+            "{} {} {}",
+            count_from,
+            if filter_condition.is_some() {
+                "AND"
             } else {
-                "".to_string()
-            }
-        } else {
-            "".to_string()
-        }
-    } else {
-        "".to_string()
+                "WHERE"
+            },
+            cursor.condition(&read_order, &mut params)?
+        ),
+        None => count_from.clone(),
     };
 
+    let order_keys: Vec<String> = read_order.iter().map(OrderKey::sql).collect();
+    let order_by_clause = format!("ORDER BY {}", order_keys.join(", "));
+
+    // one row past the page tells whether another page follows in the reading direction
+    let fetch_limit = limit + 1;
+    let offset = if cursor.is_some() { 0 } else { offset };
+    let param_id = params.len() + 1;
+    let select_list: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            let name = quote_ident(&column.name);
+            if column.as_text {
+                format!("{}::text AS {}", name, name)
+            } else {
+                name
+            }
+        })
+        .collect();
     let query = format!(
-        "SELECT * FROM \"{}\".\"{}\" {} {} LIMIT ${} OFFSET ${}",
-        schema,
-        table,
-        where_clause,
+        "SELECT {} {} {} LIMIT ${} OFFSET ${}",
+        select_list.join(", "),
+        page_from,
         order_by_clause,
         param_id,
         param_id + 1
@@ -554,53 +684,108 @@ async fn get_table_data(
     for p in &params {
         query_params.push(p.as_ref());
     }
-    query_params.push(&limit);
+    query_params.push(&fetch_limit);
     query_params.push(&offset);
 
-    let rows = client
-        .query(query.as_str(), &query_params)
-        .await
-        .map_err(|e| e.to_string())?;
+    let (rows, mut data, row_count) = request
+        .run(client.cancel_token(), async {
+            let mut transaction = client.build_transaction().read_only(true).start().await?;
+            if let Some(timeouts) = timeouts {
+                timeouts.set_local(&transaction).await?;
+            }
+            let rows = transaction.query(query.as_str(), &query_params).await?;
+            let row_count = count::count_rows(
+                &mut transaction,
+                &count_from,
+                &query_params[..filter_param_count],
+            )
+            .await?;
+            let data = cell::read_rows(&mut transaction, &rows).await?;
+            transaction.commit().await?;
+            Ok((rows, data, row_count))
+        })
+        .await?;
 
-    let data = rows
-        .iter()
+    let has_more = data.len() as i64 > limit;
+    data.truncate(limit as usize);
+    let direction = cursor.as_ref().map(|cursor| cursor.direction);
+    if direction == Some(Direction::Prev) {
+        data.reverse();
+    }
+
+    let key_indices: Vec<usize> = rows
+        .first()
         .map(|row| {
-            row.columns()
+            order
                 .iter()
-                .enumerate()
-                .map(|(i, col)| cell_to_json(row, col.type_(), i))
+                .filter_map(|key| row.columns().iter().position(|c| c.name() == key.column))
                 .collect()
         })
-        .collect();
+        .unwrap_or_default();
+
+    let (has_prev, has_next) = match direction {
+        None => (offset > 0, has_more),
+        Some(Direction::Next) => (true, has_more),
+        Some(Direction::Prev) => (has_more, true),
+    };
+    let boundary_cursor = |row: Option<&Vec<Cell>>, direction| {
+        let row = row.filter(|_| keyset_supported)?;
+        let values = key_indices
+            .iter()
+            .map(|&i| row[i].value())
+            .collect::<Option<_>>()?;
+        Some(Cursor::new(direction, &order, values).encode())
+    };
 
     Ok(TableData {
         columns,
+        row_count,
+        prev_cursor: boundary_cursor(data.first().filter(|_| has_prev), Direction::Prev),
+        next_cursor: boundary_cursor(data.last().filter(|_| has_next), Direction::Next),
         rows: data,
+        order_by: order,
     })
 }
 
 #[tauri::command]
 async fn get_table_column_types(
-    uri: &str,
+    registry: State<'_, ConnectionRegistry>,
+    session_id: &str,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    let client = registry.get(session_id).await?;
+    query_column_types(&client, schema, table).await
+}
+
+async fn query_primary_keys(
+    client: &tokio_postgres::Client,
     schema: &str,
     table: &str,
-) -> Result<Vec<(String, String)>, String> {
-    let config = tokio_postgres::Config::from_str(uri).map_err(|e| e.to_string())?;
+) -> Result<Vec<String>, Error> {
+    let query = "
+        SELECT kcu.column_name
+        FROM information_schema.key_column_usage AS kcu
+        JOIN information_schema.table_constraints AS tc
+        ON kcu.constraint_name = tc.constraint_name AND kcu.table_schema = tc.table_schema
+        WHERE tc.constraint_type = 'PRIMARY KEY'
+        AND kcu.table_schema = $1
+        AND kcu.table_name = $2
+        ORDER BY kcu.ordinal_position;
+    ";
 
-    let tls_config = rustls::ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerification))
-        .with_no_client_auth();
-    let tls = MakeRustlsConnect::new(tls_config);
+    let rows = client.query(query, &[&schema, &table]).await?;
 
-    let (client, connection) = config.connect(tls).await.map_err(|e| e.to_string())?;
+    let pks = rows.iter().map(|row| row.get("column_name")).collect();
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
+    Ok(pks)
+}
 
+async fn query_column_types(
+    client: &tokio_postgres::Client,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<(String, String)>, Error> {
     let query = "
         SELECT column_name, udt_name
         FROM information_schema.columns
@@ -608,10 +793,7 @@ async fn get_table_column_types(
         ORDER BY ordinal_position;
     ";
 
-    let rows = client
-        .query(query, &[&schema, &table])
-        .await
-        .map_err(|e| e.to_string())?;
+    let rows = client.query(query, &[&schema, &table]).await?;
 
     let types = rows
         .iter()
@@ -621,68 +803,67 @@ async fn get_table_column_types(
     Ok(types)
 }
 
-#[tauri::command]
-fn cell_to_json(row: &Row, col_type: &Type, idx: usize) -> Value {
-    match *col_type {
-        Type::BOOL => row
-            .try_get::<_, Option<bool>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::INT2 => row
-            .try_get::<_, Option<i16>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::INT4 => row
-            .try_get::<_, Option<i32>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::INT8 => row
-            .try_get::<_, Option<i64>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::FLOAT4 => row
-            .try_get::<_, Option<f32>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::FLOAT8 => row
-            .try_get::<_, Option<f64>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::TEXT | Type::VARCHAR | Type::NAME => row
-            .try_get::<_, Option<String>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v)),
-        Type::UUID => row
-            .try_get::<_, Option<Uuid>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v.to_string())),
-        Type::DATE => row
-            .try_get::<_, Option<chrono::NaiveDate>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v.to_string())),
-        Type::TIMESTAMP => row
-            .try_get::<_, Option<chrono::NaiveDateTime>>(idx)
-            .unwrap_or_default()
-            .map(|dt| serde_json::json!(dt.to_string()))
-            .unwrap_or(Value::Null),
-        Type::JSON | Type::JSONB => row
-            .try_get::<_, Option<serde_json::Value>>(idx)
-            .unwrap_or_default()
-            .unwrap_or(Value::Null),
-        Type::TIMESTAMPTZ => row
-            .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(idx)
-            .unwrap_or_default()
-            .map_or(Value::Null, |v| serde_json::json!(v.to_string())),
-        _ => {
-            if col_type.name() == "vector" {
-                row.try_get::<_, Option<Vector>>(idx)
-                    .unwrap_or_default()
-                    .map_or(Value::Null, |v| serde_json::json!(v.to_vec()))
-            } else {
-                row.try_get::<_, Option<String>>(idx)
-                    .unwrap_or_default()
-                    .map_or(Value::Null, |v| serde_json::json!(v))
-            }
-        }
+// json has no NaN or infinities, and serde_json would make them null, so they go
+// as the strings postgres prints for them, which to_sql_value parses back
+fn float_to_json<T: Copy + Into<f64>>(value: T) -> Value
+where
+    Value: From<T>,
+{
+    let float: f64 = value.into();
+    if float.is_nan() {
+        Value::String("NaN".to_string())
+    } else if float.is_infinite() {
+        Value::String(if float > 0.0 { "Infinity" } else { "-Infinity" }.to_string())
+    } else {
+        Value::from(value)
     }
 }
+
+// also decodes array elements, which is why it works on raw bytes instead of a row
+fn value_to_json(
+    col_type: &Type,
+    raw: &[u8],
+) -> Result<Value, Box<dyn std::error::Error + Sync + Send>> {
+    let value = match *col_type {
+        Type::BOOL => serde_json::json!(bool::from_sql(col_type, raw)?),
+        Type::INT2 => serde_json::json!(i16::from_sql(col_type, raw)?),
+        Type::INT4 => serde_json::json!(i32::from_sql(col_type, raw)?),
+        Type::INT8 => serde_json::json!(i64::from_sql(col_type, raw)?),
+        Type::FLOAT4 => float_to_json(f32::from_sql(col_type, raw)?),
+        Type::FLOAT8 => float_to_json(f64::from_sql(col_type, raw)?),
+        // sent as a string so no digit is lost to a javascript number
+        Type::NUMERIC => serde_json::json!(Numeric::from_sql(col_type, raw)?.to_string()),
+        Type::TEXT | Type::VARCHAR | Type::NAME => {
+            serde_json::json!(String::from_sql(col_type, raw)?)
+        }
+        Type::UUID => serde_json::json!(Uuid::from_sql(col_type, raw)?.to_string()),
+        Type::DATE => serde_json::json!(NaiveDate::from_sql(col_type, raw)?.to_string()),
+        Type::TIMESTAMP => serde_json::json!(NaiveDateTime::from_sql(col_type, raw)?.to_string()),
+        Type::TIME => serde_json::json!(Time::from_sql(col_type, raw)?.to_string()),
+        Type::TIMETZ => serde_json::json!(TimeTz::from_sql(col_type, raw)?.to_string()),
+        Type::INTERVAL => serde_json::json!(Interval::from_sql(col_type, raw)?.to_string()),
+        Type::JSON | Type::JSONB => Value::from_sql(col_type, raw)?,
+        Type::TIMESTAMPTZ => {
+            serde_json::json!(DateTime::<Utc>::from_sql(col_type, raw)?.to_string())
+        }
+        _ => match col_type.kind() {
+            Kind::Array(member) => array::decode(member, raw, value_to_json)?,
+            _ if col_type.name() == "vector" => {
+                serde_json::json!(Vector::from_sql(col_type, raw)?.to_vec())
+            }
+            _ if <String as FromSql>::accepts(col_type) => {
+                serde_json::json!(String::from_sql(col_type, raw)?)
+            }
+            _ => return Err(format!("unsupported type {}", col_type).into()),
+        },
+    };
+    Ok(value)
+}
+
+// what a value decodes to after being sent to the server in binary
+#[cfg(test)]
+fn binary_round_trip<T: ToSql + for<'a> FromSql<'a>>(value: &T, ty: &Type) -> T {
+    let mut out = BytesMut::new();
+    value.to_sql(ty, &mut out).unwrap();
+    T::from_sql(ty, &out).unwrap()
+}
@@ -0,0 +1,276 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+const POSITIVE: u16 = 0x0000;
+const NEGATIVE: u16 = 0x4000;
+const NAN: u16 = 0xC000;
+const INFINITY: u16 = 0xD000;
+const NEGATIVE_INFINITY: u16 = 0xF000;
+
+// a numeric in postgres' own binary layout: base 10000 digits, the weight of the
+// first one and the number of decimal places to show, so any precision and scale
+// goes through unchanged instead of being rounded to a float
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Numeric {
+    sign: u16,
+    weight: i16,
+    scale: u16,
+    digits: Vec<i16>,
+}
+
+impl fmt::Display for Numeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sign {
+            NAN => return f.write_str("NaN"),
+            INFINITY => return f.write_str("Infinity"),
+            NEGATIVE_INFINITY => return f.write_str("-Infinity"),
+            NEGATIVE => f.write_str("-")?,
+            _ => {}
+        }
+
+        let digit = |group: i32| -> i16 {
+            usize::try_from(group)
+                .ok()
+                .and_then(|group| self.digits.get(group))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let weight = i32::from(self.weight);
+        if weight < 0 {
+            f.write_str("0")?;
+        } else {
+            write!(f, "{}", digit(0))?;
+            for group in 1..=weight {
+                write!(f, "{:04}", digit(group))?;
+            }
+        }
+
+        if self.scale > 0 {
+            let mut fraction = String::new();
+            let mut group = weight + 1;
+            while fraction.len() < usize::from(self.scale) {
+                fraction.push_str(&format!("{:04}", digit(group)));
+                group += 1;
+            }
+            fraction.truncate(usize::from(self.scale));
+            write!(f, ".{}", fraction)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Numeric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let special = |sign| Numeric {
+            sign,
+            weight: 0,
+            scale: 0,
+            digits: Vec::new(),
+        };
+        match s.to_ascii_lowercase().as_str() {
+            "nan" => return Ok(special(NAN)),
+            "infinity" | "+infinity" | "inf" | "+inf" => return Ok(special(INFINITY)),
+            "-infinity" | "-inf" => return Ok(special(NEGATIVE_INFINITY)),
+            _ => {}
+        }
+
+        let invalid = || "expected digits with an optional sign, point and exponent".to_string();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(at) => (
+                &unsigned[..at],
+                unsigned[at + 1..].parse::<i32>().map_err(|_| invalid())?,
+            ),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty() && fraction.is_empty()
+            || !all_digits(integer)
+            || !all_digits(fraction)
+        {
+            return Err(invalid());
+        }
+
+        // the decimal digits and where the point falls among them once the exponent is applied
+        let mut decimals: Vec<u8> = integer
+            .bytes()
+            .chain(fraction.bytes())
+            .map(|b| b - b'0')
+            .collect();
+        let point = i64::try_from(integer.len()).map_err(|_| invalid())? + i64::from(exponent);
+        // postgres' own limits: 131072 digits before the point and 16383 after it
+        if !(-16383..=131072).contains(&point) {
+            return Err("value is out of numeric range".to_string());
+        }
+        let scale = u16::try_from((decimals.len() as i64 - point).max(0)).map_err(|_| invalid())?;
+
+        // pad both ends so the point sits on a group boundary and every group has four digits
+        let mut point = point;
+        if point < 0 {
+            let missing = usize::try_from(-point).map_err(|_| invalid())?;
+            decimals.splice(0..0, std::iter::repeat_n(0, missing));
+            point = 0;
+        }
+        let point = usize::try_from(point).map_err(|_| invalid())?;
+        if point > decimals.len() {
+            decimals.resize(point, 0);
+        }
+        let lead = (4 - point % 4) % 4;
+        decimals.splice(0..0, std::iter::repeat_n(0, lead));
+        decimals.resize(decimals.len().div_ceil(4) * 4, 0);
+
+        let mut digits: Vec<i16> = decimals
+            .chunks(4)
+            .map(|group| group.iter().fold(0i16, |acc, &d| acc * 10 + i16::from(d)))
+            .collect();
+        let integer_groups = (point + lead) / 4;
+        let mut weight = i64::try_from(integer_groups).map_err(|_| invalid())? - 1;
+
+        let leading_zeros = digits.iter().take_while(|&&d| d == 0).count();
+        digits.drain(..leading_zeros);
+        weight -= leading_zeros as i64;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        Ok(Numeric {
+            sign: if negative && !digits.is_empty() {
+                NEGATIVE
+            } else {
+                POSITIVE
+            },
+            weight: i16::try_from(weight).map_err(|_| invalid())?,
+            scale,
+            digits,
+        })
+    }
+}
+
+impl<'a> FromSql<'a> for Numeric {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() < 8 {
+            return Err("invalid numeric message".into());
+        }
+        let count = raw.get_i16();
+        let weight = raw.get_i16();
+        let sign = raw.get_u16();
+        let scale = raw.get_u16();
+        let count = usize::try_from(count).map_err(|_| "invalid numeric digit count")?;
+        if raw.len() != count * 2 {
+            return Err("invalid numeric message".into());
+        }
+        let digits = (0..count).map(|_| raw.get_i16()).collect();
+        Ok(Numeric {
+            sign,
+            weight,
+            scale,
+            digits,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+impl ToSql for Numeric {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_i16(i16::try_from(self.digits.len())?);
+        out.put_i16(self.weight);
+        out.put_u16(self.sign);
+        out.put_u16(self.scale);
+        for digit in &self.digits {
+            out.put_i16(*digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_round_trip;
+
+    fn wire(text: &str) -> Vec<u8> {
+        let mut out = BytesMut::new();
+        let value: Numeric = text.parse().unwrap();
+        value.to_sql(&Type::NUMERIC, &mut out).unwrap();
+        out.to_vec()
+    }
+
+    #[test]
+    fn round_trips_without_rounding() {
+        for (input, text) in [
+            ("0", "0"),
+            ("0.0001", "0.0001"),
+            ("12345.678", "12345.678"),
+            (
+                "-12345678901234567890.000100",
+                "-12345678901234567890.000100",
+            ),
+            ("1e-20", "0.00000000000000000001"),
+            ("1.2e3", "1200"),
+            ("+.5", "0.5"),
+            ("10000", "10000"),
+            ("1.500", "1.500"),
+            ("-0.00", "0.00"),
+            ("-0", "0"),
+            ("NaN", "NaN"),
+            ("Infinity", "Infinity"),
+            ("-inf", "-Infinity"),
+        ] {
+            let value: Numeric = input.parse().unwrap();
+            assert_eq!(value.to_string(), text, "{}", input);
+            assert_eq!(text.parse::<Numeric>(), Ok(value.clone()), "{}", input);
+            assert_eq!(binary_round_trip(&value, &Type::NUMERIC), value);
+        }
+    }
+
+    // what numeric_send gives for the same values
+    #[test]
+    fn writes_the_layout_postgres_sends() {
+        for (text, bytes) in [
+            ("12345.678", "0003000100000003000109291a7c"),
+            ("0.0001", "0001ffff000000040001"),
+            (
+                "-12345678901234567890.000100",
+                "000600044000000604d2162e23340d801ed20001",
+            ),
+            ("1e-20", "0001fffb000000140001"),
+            ("-0.00", "0000000000000002"),
+            ("NaN", "00000000c0000000"),
+        ] {
+            let hex: String = wire(text).iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(hex, bytes, "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for input in [
+            "", ".", "1.2.3", "1e", "e5", "--1", "1,5", "0x10", "1e131073",
+        ] {
+            assert!(input.parse::<Numeric>().is_err(), "{}", input);
+        }
+    }
+}
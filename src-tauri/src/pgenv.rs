@@ -0,0 +1,322 @@
+use std::path::{Path, PathBuf};
+use tokio_postgres::config::Host;
+use tokio_postgres::Config;
+
+use crate::connstr;
+use crate::tls::TlsOptions;
+
+// libpq keywords we can pass on; anything else in a service file is skipped
+const SUPPORTED_KEYWORDS: &[&str] = &[
+    "host",
+    "hostaddr",
+    "port",
+    "dbname",
+    "user",
+    "password",
+    "options",
+    "application_name",
+    "connect_timeout",
+    "sslmode",
+    "sslrootcert",
+    "sslcert",
+    "sslkey",
+    "sslpassword",
+];
+
+const ENV_KEYWORDS: &[(&str, &str)] = &[
+    ("PGHOST", "host"),
+    ("PGHOSTADDR", "hostaddr"),
+    ("PGPORT", "port"),
+    ("PGDATABASE", "dbname"),
+    ("PGUSER", "user"),
+    ("PGPASSWORD", "password"),
+    ("PGOPTIONS", "options"),
+    ("PGAPPNAME", "application_name"),
+    ("PGCONNECT_TIMEOUT", "connect_timeout"),
+    ("PGSSLMODE", "sslmode"),
+    ("PGSSLROOTCERT", "sslrootcert"),
+    ("PGSSLCERT", "sslcert"),
+    ("PGSSLKEY", "sslkey"),
+];
+
+pub const DEFAULT_PORT: u16 = 5432;
+
+#[derive(serde::Serialize, Debug)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub file: String,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub dbname: Option<String>,
+    pub user: Option<String>,
+}
+
+type Section = (String, Vec<(String, String)>);
+
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env_var("HOME")
+        .or_else(|| env_var("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+// user file first, so its sections shadow the system-wide ones like in libpq
+fn service_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    match env_var("PGSERVICEFILE") {
+        Some(path) => files.push(PathBuf::from(path)),
+        None => files.extend(home_dir().map(|home| home.join(".pg_service.conf"))),
+    }
+    match env_var("PGSYSCONFDIR") {
+        Some(dir) => files.push(PathBuf::from(dir).join("pg_service.conf")),
+        None if cfg!(unix) => {
+            files.push(PathBuf::from("/etc/postgresql-common/pg_service.conf"));
+            files.push(PathBuf::from("/etc/pg_service.conf"));
+        }
+        None => {}
+    }
+    files
+}
+
+fn read_service_file(path: &Path) -> Result<Vec<Section>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read service file {}: {}", path.display(), e))?;
+
+    let mut sections: Vec<Section> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "syntax error in service file {}, line {}",
+                path.display(),
+                i + 1
+            ));
+        };
+        let Some((_, params)) = sections.last_mut() else {
+            return Err(format!(
+                "syntax error in service file {}, line {}: parameter outside of a service",
+                path.display(),
+                i + 1
+            ));
+        };
+        params.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    Ok(sections)
+}
+
+pub fn list_services() -> Result<Vec<ServiceEntry>, String> {
+    let mut services: Vec<ServiceEntry> = Vec::new();
+
+    for file in service_files().into_iter().filter(|file| file.exists()) {
+        for (name, params) in read_service_file(&file)? {
+            if services.iter().any(|service| service.name == name) {
+                continue;
+            }
+            let get = |key: &str| {
+                params
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v.clone())
+            };
+            services.push(ServiceEntry {
+                host: get("host").or_else(|| get("hostaddr")),
+                port: get("port"),
+                dbname: get("dbname"),
+                user: get("user"),
+                name,
+                file: file.display().to_string(),
+            });
+        }
+    }
+
+    Ok(services)
+}
+
+pub fn service_layer(name: &str) -> Result<(Config, TlsOptions), String> {
+    for file in service_files().into_iter().filter(|file| file.exists()) {
+        if let Some((_, params)) = read_service_file(&file)?
+            .into_iter()
+            .find(|(section, _)| section == name)
+        {
+            return parse_keywords(&params);
+        }
+    }
+    Err(format!("definition of service \"{}\" not found", name))
+}
+
+pub fn env_layer() -> Result<(Config, TlsOptions), String> {
+    let params: Vec<(String, String)> = ENV_KEYWORDS
+        .iter()
+        .filter_map(|(var, key)| env_var(var).map(|value| (key.to_string(), value)))
+        .collect();
+    parse_keywords(&params)
+}
+
+fn parse_keywords(params: &[(String, String)]) -> Result<(Config, TlsOptions), String> {
+    let conn_str = connstr::to_key_value(
+        params
+            .iter()
+            .filter(|(key, _)| SUPPORTED_KEYWORDS.contains(&key.as_str()))
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    let (conn_str, tls) = TlsOptions::extract(&conn_str)?;
+    let config = conn_str.parse::<Config>().map_err(|e| e.to_string())?;
+    Ok((config, tls))
+}
+
+// copies every setting `target` does not have yet from a lower-precedence source
+pub fn fill_config(target: &mut Config, source: &Config) {
+    if target.get_hosts().is_empty() && target.get_hostaddrs().is_empty() {
+        for host in source.get_hosts() {
+            match host {
+                Host::Tcp(host) => target.host(host),
+                #[cfg(unix)]
+                Host::Unix(path) => target.host_path(path),
+            };
+        }
+        for hostaddr in source.get_hostaddrs() {
+            target.hostaddr(*hostaddr);
+        }
+    }
+    if target.get_ports().is_empty() {
+        for port in source.get_ports() {
+            target.port(*port);
+        }
+    }
+    if target.get_user().is_none() {
+        if let Some(user) = source.get_user() {
+            target.user(user);
+        }
+    }
+    if target.get_password().is_none() {
+        if let Some(password) = source.get_password() {
+            target.password(password);
+        }
+    }
+    if target.get_dbname().is_none() {
+        if let Some(dbname) = source.get_dbname() {
+            target.dbname(dbname);
+        }
+    }
+    if target.get_options().is_none() {
+        if let Some(options) = source.get_options() {
+            target.options(options);
+        }
+    }
+    if target.get_application_name().is_none() {
+        if let Some(application_name) = source.get_application_name() {
+            target.application_name(application_name);
+        }
+    }
+    if target.get_connect_timeout().is_none() {
+        if let Some(timeout) = source.get_connect_timeout() {
+            target.connect_timeout(*timeout);
+        }
+    }
+}
+
+// appends `-c name=value` to the startup options, which the server applies like SET
+pub fn add_server_setting(config: &mut Config, name: &str, value: &str) {
+    let options = match config.get_options() {
+        Some(options) if !options.is_empty() => format!("{} -c {}={}", options, name, value),
+        _ => format!("-c {}={}", name, value),
+    };
+    config.options(&options);
+}
+
+pub fn apply_defaults(config: &mut Config) {
+    if config.get_hosts().is_empty() && config.get_hostaddrs().is_empty() {
+        config.host("localhost");
+    }
+    if config.get_user().is_none() {
+        if let Some(user) = env_var("USER").or_else(|| env_var("USERNAME")) {
+            config.user(&user);
+        }
+    }
+}
+
+fn pgpass_file() -> Option<PathBuf> {
+    if let Some(path) = env_var("PGPASSFILE") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        env_var("APPDATA").map(|dir| PathBuf::from(dir).join("postgresql").join("pgpass.conf"))
+    } else {
+        home_dir().map(|home| home.join(".pgpass"))
+    }
+}
+
+// looks up the first matching hostname:port:database:username:password line
+pub fn pgpass_password(config: &Config) -> Option<String> {
+    let path = pgpass_file().filter(|path| path.exists())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).ok()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            eprintln!(
+                "password file {} has group or world access; permissions should be u=rw (0600) or less",
+                path.display()
+            );
+            return None;
+        }
+    }
+
+    let host = match config.get_hosts().first() {
+        Some(Host::Tcp(host)) => host.clone(),
+        #[cfg(unix)]
+        Some(Host::Unix(_)) => "localhost".to_string(),
+        None => config.get_hostaddrs().first()?.to_string(),
+    };
+    let port = config
+        .get_ports()
+        .first()
+        .copied()
+        .unwrap_or(DEFAULT_PORT)
+        .to_string();
+    let user = config.get_user()?;
+    let dbname = config.get_dbname().unwrap_or(user);
+
+    let contents = std::fs::read_to_string(&path).ok()?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(split_pgpass_line)
+        .find(|fields| {
+            fields.len() == 5
+                && [host.as_str(), port.as_str(), dbname, user]
+                    .iter()
+                    .zip(fields.iter())
+                    .all(|(value, field)| field == "*" || field == value)
+        })
+        .map(|mut fields| fields.remove(4))
+}
+
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    fields.last_mut().unwrap().push(escaped);
+                }
+            }
+            ':' if fields.len() < 5 => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(ch),
+        }
+    }
+    fields
+}
@@ -0,0 +1,307 @@
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod, Runtime};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::{CancelToken, NoTls};
+use tokio_postgres_rustls::MakeRustlsConnect;
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::profile::{describe_hosts, uses_unix_socket, ConnectionProfile};
+use crate::tunnel::Tunnel;
+
+const MAX_POOL_SIZE: usize = 4;
+const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+pub type SessionId = String;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub name: Option<String>,
+    pub host: String,
+    pub dbname: Option<String>,
+    pub user: Option<String>,
+    pub read_only: bool,
+    pub connected_at: chrono::DateTime<chrono::Utc>,
+}
+
+struct Session {
+    info: SessionInfo,
+    pool: Pool,
+    // cancel requests open their own connection, so they need the same tls setup
+    cancel_tls: Option<MakeRustlsConnect>,
+    // kept alive for as long as the session; dropping it tears the tunnel down
+    _tunnel: Option<Tunnel>,
+}
+
+struct RunningQuery {
+    session_id: SessionId,
+    // only set while the query itself runs; the command does its lookups before
+    token: Option<CancelToken>,
+    cancelled: Arc<AtomicBool>,
+    // held while a cancel request is on its way, so the client isn't given back
+    // to the pool and reused by another query the cancel could then hit
+    cancelling: Arc<tokio::sync::Mutex<()>>,
+}
+
+// each session owns a pool built from its profile; credentials never leave this process
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    sessions: Arc<Mutex<HashMap<SessionId, Session>>>,
+    running: Arc<Mutex<HashMap<String, RunningQuery>>>,
+}
+
+impl ConnectionRegistry {
+    pub async fn connect(&self, profile: &ConnectionProfile) -> Result<SessionId, Error> {
+        let (mut config, tls_options) = profile.resolve().map_err(Error::validation)?;
+
+        let info = SessionInfo {
+            id: Uuid::new_v4().to_string(),
+            name: profile.name.clone(),
+            host: describe_hosts(&config),
+            dbname: config.get_dbname().map(str::to_string),
+            user: config.get_user().map(str::to_string),
+            read_only: profile.read_only,
+            connected_at: chrono::Utc::now(),
+        };
+
+        let tunnel = match &profile.ssh {
+            Some(ssh) => {
+                let tunnel = Tunnel::open(ssh, &config).await?;
+                config = tunnel.route(&config, &tls_options);
+                Some(tunnel)
+            }
+            None => None,
+        };
+
+        let manager_config = ManagerConfig {
+            // runs an empty query before handing out a recycled client,
+            // so connections dropped by the server are replaced transparently
+            recycling_method: RecyclingMethod::Verified,
+        };
+        let (manager, cancel_tls) = if uses_unix_socket(&config) {
+            config.ssl_mode(SslMode::Disable);
+            (Manager::from_config(config, NoTls, manager_config), None)
+        } else {
+            let connector = tls_options.connector().map_err(Error::tls)?;
+            (
+                Manager::from_config(config, connector.clone(), manager_config),
+                Some(connector),
+            )
+        };
+        let pool = Pool::builder(manager)
+            .max_size(MAX_POOL_SIZE)
+            .wait_timeout(Some(WAIT_TIMEOUT))
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(|e| Error::connection(e.to_string()))?;
+
+        // open the first connection right away so bad credentials fail the connect call;
+        // through a tunnel, a refused forward only shows up as a closed connection
+        if let Err(e) = checkout(&pool).await {
+            return Err(match tunnel.as_ref().and_then(Tunnel::take_failure) {
+                Some(reason) => Error::connection(reason),
+                None => e,
+            });
+        }
+
+        let id = info.id.clone();
+        self.sessions.lock().unwrap().insert(
+            id.clone(),
+            Session {
+                info,
+                pool,
+                cancel_tls,
+                _tunnel: tunnel,
+            },
+        );
+
+        Ok(id)
+    }
+
+    pub fn disconnect(&self, session_id: &str) -> Result<(), Error> {
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(session_id)
+            .ok_or_else(|| unknown_session(session_id))?;
+        session.pool.close();
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .map(|session| session.info.clone())
+            .collect();
+        sessions.sort_by_key(|info| info.connected_at);
+        sessions
+    }
+
+    pub async fn get(&self, session_id: &str) -> Result<Object, Error> {
+        let (pool, _) = self.pool(session_id)?;
+        checkout(&pool).await
+    }
+
+    // every command that modifies data must check out its client through here
+    pub async fn get_writable(&self, session_id: &str) -> Result<Object, Error> {
+        let (pool, read_only) = self.pool(session_id)?;
+        if read_only {
+            return Err(Error::read_only("this connection is read-only"));
+        }
+        checkout(&pool).await
+    }
+
+    fn pool(&self, session_id: &str) -> Result<(Pool, bool), Error> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .map(|session| (session.pool.clone(), session.info.read_only))
+            .ok_or_else(|| unknown_session(session_id))
+    }
+
+    // registers `request_id` for the whole command, so a cancel that comes in
+    // before its query has started is kept instead of being lost
+    pub fn track(&self, session_id: &str, request_id: Option<&str>) -> TrackedRequest {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelling = Arc::new(tokio::sync::Mutex::new(()));
+        if let Some(request_id) = request_id {
+            self.running.lock().unwrap().insert(
+                request_id.to_string(),
+                RunningQuery {
+                    session_id: session_id.to_string(),
+                    token: None,
+                    cancelled: cancelled.clone(),
+                    cancelling: cancelling.clone(),
+                },
+            );
+        }
+        TrackedRequest {
+            running: self.running.clone(),
+            request_id: request_id.map(str::to_string),
+            cancelled,
+            cancelling,
+        }
+    }
+
+    pub async fn cancel(&self, request_id: &str) -> Result<(), Error> {
+        let (token, cancel_tls, _cancelling) = {
+            let running = self.running.lock().unwrap();
+            let query = running
+                .get(request_id)
+                .ok_or_else(|| Error::not_found(format!("no running query: {}", request_id)))?;
+            query.cancelled.store(true, Ordering::SeqCst);
+            // not started yet; run refuses to start it
+            let Some(token) = query.token.clone() else {
+                return Ok(());
+            };
+            // taken while the token is still set, so run can't have returned its
+            // client yet; failing means a cancel is already under way
+            let Ok(cancelling) = query.cancelling.clone().try_lock_owned() else {
+                return Ok(());
+            };
+            let cancel_tls = self
+                .sessions
+                .lock()
+                .unwrap()
+                .get(&query.session_id)
+                .and_then(|session| session.cancel_tls.clone());
+            (token, cancel_tls, cancelling)
+        };
+
+        match cancel_tls {
+            Some(tls) => token.cancel_query(tls).await?,
+            None => token.cancel_query(NoTls).await?,
+        }
+        Ok(())
+    }
+
+    pub fn evict_idle(&self) {
+        let sessions = self.sessions.lock().unwrap();
+        for session in sessions.values() {
+            session
+                .pool
+                .retain(|_, metrics| metrics.last_used() < IDLE_TIMEOUT);
+        }
+    }
+
+    pub fn spawn_reaper(&self) {
+        let registry = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                registry.evict_idle();
+            }
+        });
+    }
+}
+
+// a command that can be cancelled by its request id; dropping it unregisters the id
+pub struct TrackedRequest {
+    running: Arc<Mutex<HashMap<String, RunningQuery>>>,
+    request_id: Option<String>,
+    cancelled: Arc<AtomicBool>,
+    cancelling: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl TrackedRequest {
+    // runs `work` with the client `token` belongs to, so cancel can interrupt it on the server
+    pub async fn run<T>(
+        &self,
+        token: CancelToken,
+        work: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let Some(request_id) = &self.request_id else {
+            return work.await;
+        };
+
+        {
+            let mut running = self.running.lock().unwrap();
+            if self.cancelled.load(Ordering::SeqCst) {
+                return Err(Error::cancelled());
+            }
+            if let Some(query) = running.get_mut(request_id) {
+                query.token = Some(token);
+            }
+        }
+        let result = work.await;
+        if let Some(query) = self.running.lock().unwrap().get_mut(request_id) {
+            query.token = None;
+        }
+        // a cancel that found the query still running has to settle first
+        let _ = self.cancelling.lock().await;
+
+        match result {
+            Err(_) if self.cancelled.load(Ordering::SeqCst) => Err(Error::cancelled()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for TrackedRequest {
+    fn drop(&mut self) {
+        if let Some(request_id) = &self.request_id {
+            self.running.lock().unwrap().remove(request_id);
+        }
+    }
+}
+
+async fn checkout(pool: &Pool) -> Result<Object, Error> {
+    pool.get().await.map_err(Error::from)
+}
+
+fn unknown_session(session_id: &str) -> Error {
+    Error::not_found(format!("unknown session: {}", session_id))
+}
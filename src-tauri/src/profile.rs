@@ -0,0 +1,151 @@
+use std::str::FromStr;
+use tokio_postgres::config::Host;
+use tokio_postgres::Config;
+
+use crate::connstr;
+use crate::pgenv;
+use crate::timeouts::Timeouts;
+use crate::tls::TlsOptions;
+use crate::tunnel::SshOptions;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ConnectionProfile {
+    pub name: Option<String>,
+    pub uri: Option<String>,
+    pub host: Option<String>,
+    // directory holding the server's .s.PGSQL.<port> socket, e.g. /var/run/postgresql
+    pub socket_dir: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub dbname: Option<String>,
+    pub service: Option<String>,
+    pub tls: Option<TlsOptions>,
+    pub ssh: Option<SshOptions>,
+    pub timeouts: Option<Timeouts>,
+    pub read_only: bool,
+}
+
+impl ConnectionProfile {
+    // precedence follows libpq: the uri, then the discrete fields, then the
+    // service file entry, then PG* environment variables, and finally ~/.pgpass
+    // for the password; explicit tls options replace the ssl parameters of the uri
+    pub fn resolve(&self) -> Result<(Config, TlsOptions), String> {
+        let (mut config, mut tls, mut service) = match &self.uri {
+            Some(uri) => {
+                let (conn_str, service) = connstr::strip_params(uri, &["service"]);
+                let (conn_str, tls) = TlsOptions::extract(&conn_str)?;
+                let config = Config::from_str(&conn_str).map_err(|e| e.to_string())?;
+                (
+                    config,
+                    tls,
+                    service.into_iter().next().map(|(_, name)| name),
+                )
+            }
+            None => (Config::new(), TlsOptions::default(), None),
+        };
+
+        if let Some(socket_dir) = &self.socket_dir {
+            if config.get_hosts().is_empty() {
+                #[cfg(unix)]
+                config.host_path(socket_dir);
+                #[cfg(not(unix))]
+                return Err(format!(
+                    "unix domain sockets are not supported on this platform: {}",
+                    socket_dir
+                ));
+            }
+        }
+        if let Some(host) = &self.host {
+            if config.get_hosts().is_empty() {
+                config.host(host);
+            }
+        }
+        if let Some(port) = self.port {
+            if config.get_ports().is_empty() {
+                config.port(port);
+            }
+        }
+        if let Some(user) = &self.user {
+            if config.get_user().is_none() {
+                config.user(user);
+            }
+        }
+        if let Some(password) = &self.password {
+            if config.get_password().is_none() {
+                config.password(password);
+            }
+        }
+        if let Some(dbname) = &self.dbname {
+            if config.get_dbname().is_none() {
+                config.dbname(dbname);
+            }
+        }
+        if let Some(profile_tls) = &self.tls {
+            tls = profile_tls.clone();
+        }
+
+        service = service
+            .or_else(|| self.service.clone())
+            .or_else(|| pgenv::env_var("PGSERVICE"));
+        if let Some(service) = service {
+            let (service_config, service_tls) = pgenv::service_layer(&service)?;
+            pgenv::fill_config(&mut config, &service_config);
+            tls.fill_from(service_tls);
+        }
+
+        let (env_config, env_tls) = pgenv::env_layer()?;
+        pgenv::fill_config(&mut config, &env_config);
+        tls.fill_from(env_tls);
+
+        pgenv::apply_defaults(&mut config);
+        if config.get_password().is_none() {
+            if let Some(password) = pgenv::pgpass_password(&config) {
+                config.password(password);
+            }
+        }
+
+        tls.apply(&mut config);
+        if let Some(timeouts) = &self.timeouts {
+            timeouts.apply(&mut config);
+        }
+        if self.read_only {
+            // the server then rejects writes even from statements we did not expect to write
+            pgenv::add_server_setting(&mut config, "default_transaction_read_only", "on");
+        }
+
+        Ok((config, tls))
+    }
+}
+
+// postgres never negotiates tls over a unix socket, so these connections skip it
+pub fn uses_unix_socket(config: &Config) -> bool {
+    config.get_hostaddrs().is_empty()
+        && !config.get_hosts().is_empty()
+        && config
+            .get_hosts()
+            .iter()
+            .all(|host| !matches!(host, Host::Tcp(_)))
+}
+
+pub fn describe_hosts(config: &Config) -> String {
+    let ports = config.get_ports();
+    config
+        .get_hosts()
+        .iter()
+        .enumerate()
+        .map(|(i, host)| {
+            let host = match host {
+                Host::Tcp(host) => host.clone(),
+                #[cfg(unix)]
+                Host::Unix(path) => path.display().to_string(),
+            };
+            match ports.get(i).or(ports.first()) {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,202 @@
+use pgvector::Vector;
+use serde_json::Value;
+use tokio_postgres::Client;
+
+use crate::error::Error;
+use crate::filter::Params;
+use crate::keyset::OrderKey;
+use crate::sql::{quote_ident, KnownColumns, TEXT_TYPES};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    #[serde(alias = "ASC")]
+    Asc,
+    #[serde(alias = "DESC")]
+    Desc,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+// the pgvector distance operators: <->, <=> and <#>
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    #[default]
+    L2,
+    Cosine,
+    InnerProduct,
+}
+
+impl DistanceMetric {
+    fn operator(self) -> &'static str {
+        match self {
+            DistanceMetric::L2 => "<->",
+            DistanceMetric::Cosine => "<=>",
+            // pgvector returns the negative inner product so ascending puts the closest first
+            DistanceMetric::InnerProduct => "<#>",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct VectorDistance {
+    to: Vec<f32>,
+    #[serde(default)]
+    metric: DistanceMetric,
+}
+
+// `json_path` steps are object keys (strings) or array indexes (integers); the
+// last one is read as text
+#[derive(serde::Deserialize, Debug)]
+pub struct Sort {
+    column: String,
+    #[serde(default)]
+    direction: SortDirection,
+    #[serde(default)]
+    nulls: Option<Nulls>,
+    #[serde(default)]
+    collation: Option<String>,
+    #[serde(default)]
+    json_path: Vec<Value>,
+    #[serde(default)]
+    vector_distance: Option<VectorDistance>,
+}
+
+impl Sort {
+    async fn order_key(
+        &self,
+        client: &Client,
+        known: &KnownColumns<'_>,
+        params: &mut Params,
+    ) -> Result<OrderKey, Error> {
+        let pg_type = known.type_of(&self.column)?;
+        let column = quote_ident(&self.column);
+
+        // a json path always yields text, so a collation applies to it too
+        let (mut expression, from_row, collatable) =
+            match (self.json_path.as_slice(), &self.vector_distance) {
+                ([], None) => (column, true, TEXT_TYPES.contains(&pg_type)),
+                (path, None) => (
+                    self.json_path_expression(&column, pg_type, path, params)?,
+                    false,
+                    true,
+                ),
+                ([], Some(distance)) => (
+                    self.distance_expression(&column, pg_type, distance, params)?,
+                    false,
+                    false,
+                ),
+                _ => {
+                    return Err(self.invalid("sorts by a json path or a vector distance, not both"))
+                }
+            };
+
+        if let Some(collation) = &self.collation {
+            if !collatable {
+                return Err(self.invalid("a collation only applies to text values"));
+            }
+            // preparing is enough for the server to resolve the collation for this
+            // database's encoding, which the catalog alone doesn't tell for icu ones
+            let collate = format!("COLLATE {}", quote_ident(collation));
+            if let Err(err) = client
+                .prepare(&format!("SELECT NULL::text {}", collate))
+                .await
+            {
+                return Err(self.invalid(&Error::from(err).to_string()));
+            }
+            expression = format!("{} {}", expression, collate);
+        }
+
+        Ok(
+            OrderKey::expression(&self.column, pg_type, expression, from_row).direction(
+                self.direction == SortDirection::Desc,
+                self.nulls.map(|nulls| nulls == Nulls::First),
+            ),
+        )
+    }
+
+    fn json_path_expression(
+        &self,
+        column: &str,
+        pg_type: &str,
+        path: &[Value],
+        params: &mut Params,
+    ) -> Result<String, Error> {
+        if pg_type != "json" && pg_type != "jsonb" {
+            return Err(self.invalid("a json path needs a json or jsonb column"));
+        }
+        let mut expression = column.to_string();
+        for (i, step) in path.iter().enumerate() {
+            let arrow = if i + 1 == path.len() { "->>" } else { "->" };
+            let placeholder = match step {
+                Value::String(key) => {
+                    params.push(Box::new(key.clone()));
+                    format!("${}::text", params.len())
+                }
+                Value::Number(index) => {
+                    let index = index
+                        .as_i64()
+                        .and_then(|index| i32::try_from(index).ok())
+                        .ok_or_else(|| self.invalid("json path indexes must be integers"))?;
+                    params.push(Box::new(index));
+                    format!("${}::int4", params.len())
+                }
+                _ => return Err(self.invalid("json path steps must be keys or indexes")),
+            };
+            expression = format!("{}{}{}", expression, arrow, placeholder);
+        }
+        Ok(format!("({})", expression))
+    }
+
+    fn distance_expression(
+        &self,
+        column: &str,
+        pg_type: &str,
+        distance: &VectorDistance,
+        params: &mut Params,
+    ) -> Result<String, Error> {
+        if pg_type != "vector" {
+            return Err(self.invalid("a vector distance needs a vector column"));
+        }
+        if distance.to.is_empty() {
+            return Err(self.invalid("the reference vector is empty"));
+        }
+        params.push(Box::new(Vector::from(distance.to.clone())));
+        Ok(format!(
+            "({} {} ${})",
+            column,
+            distance.metric.operator(),
+            params.len()
+        ))
+    }
+
+    fn invalid(&self, problem: &str) -> Error {
+        Error::Validation {
+            message: format!("sort on {}: {}", self.column, problem),
+            column: Some(self.column.clone()),
+            row: None,
+        }
+    }
+}
+
+// resolves the requested sorts in order, binding any values they need after
+// those already in `params`
+pub async fn order_keys(
+    sorts: &[Sort],
+    client: &Client,
+    known: &KnownColumns<'_>,
+    params: &mut Params,
+) -> Result<Vec<OrderKey>, Error> {
+    let mut order = Vec::with_capacity(sorts.len());
+    for sort in sorts {
+        order.push(sort.order_key(client, known, params).await?);
+    }
+    Ok(order)
+}
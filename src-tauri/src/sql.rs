@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+
+// every identifier that ends up in generated sql goes through here; embedded
+// quotes are doubled so names like `a"b` can neither break nor extend the statement
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// the udt names of the character string types: an empty string is a value of
+// them rather than a missing one, and they are the ones a collation applies to
+pub const TEXT_TYPES: &[&str] = &["text", "varchar", "bpchar", "name", "citext"];
+
+pub fn qualified_name(schema: &str, table: &str) -> String {
+    format!("{}.{}", quote_ident(schema), quote_ident(table))
+}
+
+// the columns a statement may reference and their udt names, as read from the catalog for its table
+pub struct KnownColumns<'a>(HashMap<&'a str, &'a str>);
+
+impl<'a> KnownColumns<'a> {
+    pub fn new(columns: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        KnownColumns(columns.into_iter().collect())
+    }
+
+    pub fn type_of(&self, column: &str) -> Result<&'a str, Error> {
+        self.0
+            .get(column)
+            .copied()
+            .ok_or_else(|| Error::Validation {
+                message: format!("unknown column: {}", column),
+                column: Some(column.to_string()),
+                row: None,
+            })
+    }
+}
@@ -0,0 +1,49 @@
+use tokio_postgres::{Config, Transaction};
+
+use crate::pgenv;
+
+// all values are in milliseconds; 0 turns the limit off like it does in postgres
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct Timeouts {
+    pub statement_timeout: Option<u64>,
+    pub lock_timeout: Option<u64>,
+    pub idle_in_transaction_session_timeout: Option<u64>,
+}
+
+impl Timeouts {
+    fn settings(&self) -> Vec<(&'static str, u64)> {
+        [
+            ("statement_timeout", self.statement_timeout),
+            ("lock_timeout", self.lock_timeout),
+            (
+                "idle_in_transaction_session_timeout",
+                self.idle_in_transaction_session_timeout,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    // sent as startup options, so every pooled connection starts with them and
+    // RESET returns to them instead of the server defaults
+    pub fn apply(&self, config: &mut Config) {
+        for (name, value) in self.settings() {
+            pgenv::add_server_setting(config, name, &value.to_string());
+        }
+    }
+
+    // overrides the session values until the transaction ends
+    pub async fn set_local(
+        &self,
+        transaction: &Transaction<'_>,
+    ) -> Result<(), tokio_postgres::Error> {
+        for (name, value) in self.settings() {
+            transaction
+                .batch_execute(&format!("SET LOCAL {} = {}", name, value))
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -21,18 +21,53 @@ import {
 } from "@/components/ui/dialog";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
-import { CaretUpDown, Plus, Trash } from "@phosphor-icons/react";
+import {
+  CaretUpDown,
+  Lock,
+  LockOpen,
+  Plus,
+  Trash,
+} from "@phosphor-icons/react";
+
+export type VaultStatus = {
+  exists: boolean;
+  unlocked: boolean;
+};
+
+// what the backend shows of a saved profile; its secrets stay in the vault
+export type ProfileSummary = {
+  name: string;
+  host: string;
+  user: string | null;
+  dbname: string | null;
+  has_password: boolean;
+  read_only: boolean;
+};
 
 interface ConnectionManagerProps {
-  connections: Record<string, string>;
-  onSelect: (uri: string) => void;
-  onSave: (name: string, uri: string) => void;
+  vault: VaultStatus | undefined;
+  profiles: ProfileSummary[];
+  selected: string | null;
+  onUnlock: (password: string) => Promise<void>;
+  onLock: () => void;
+  onSelect: (name: string) => void;
+  onSave: (name: string, uri: string) => Promise<void>;
   onDelete: (name: string) => void;
   currentUri: string;
 }
 
+export function describeProfile(profile: ProfileSummary) {
+  const user = profile.user ? `${profile.user}@` : "";
+  const dbname = profile.dbname ? `/${profile.dbname}` : "";
+  return `${user}${profile.host}${dbname}`;
+}
+
 export function ConnectionManager({
-  connections,
+  vault,
+  profiles,
+  selected,
+  onUnlock,
+  onLock,
   onSelect,
   onSave,
   onDelete,
@@ -41,15 +76,35 @@ export function ConnectionManager({
   const [popoverOpen, setPopoverOpen] = useState(false);
   const [dialogOpen, setDialogOpen] = useState(false);
   const [newConnectionName, setNewConnectionName] = useState("");
+  const [password, setPassword] = useState("");
+  const [error, setError] = useState<string | null>(null);
 
-  const handleSave = () => {
+  const handleUnlock = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      await onUnlock(password);
+      setPassword("");
+      setError(null);
+    } catch (err) {
+      setError((err as Error).message);
+    }
+  };
+
+  const handleSave = async () => {
     if (newConnectionName) {
-      onSave(newConnectionName, currentUri);
-      setNewConnectionName("");
-      setDialogOpen(false);
+      try {
+        await onSave(newConnectionName, currentUri);
+        setNewConnectionName("");
+        setError(null);
+        setDialogOpen(false);
+      } catch (err) {
+        setError((err as Error).message);
+      }
     }
   };
 
+  const unlocked = vault?.unlocked ?? false;
+
   return (
     <>
       <Popover open={popoverOpen} onOpenChange={setPopoverOpen}>
@@ -59,54 +114,83 @@ export function ConnectionManager({
             role="combobox"
             className="w-56 justify-between"
           >
-            {Object.entries(connections).find(
-              ([_, uri]) => uri === currentUri
-            )?.[0] || "Select Connection"}
+            {selected || "Select Connection"}
             <CaretUpDown className="ml-2 h-4 w-4 shrink-0 opacity-50" />
           </Button>
         </PopoverTrigger>
         <PopoverContent className="w-56 p-0">
-          <Command>
-            <CommandInput placeholder="Search connections..." />
-            <CommandList>
-              <CommandEmpty>No connections found.</CommandEmpty>
-              <CommandGroup>
-                {Object.entries(connections).map(([name, uri]) => (
-                  <CommandItem
-                    key={name}
-                    onSelect={() => {
-                      onSelect(uri);
-                      setPopoverOpen(false);
-                    }}
-                    className="flex justify-between items-center"
-                  >
-                    <span>{name}</span>
-                    <Button
-                      variant="ghost"
-                      size="icon"
-                      className="h-6 w-6"
-                      onClick={(e) => {
-                        e.stopPropagation();
-                        onDelete(name);
+          {unlocked ? (
+            <Command>
+              <CommandInput placeholder="Search connections..." />
+              <CommandList>
+                <CommandEmpty>No connections found.</CommandEmpty>
+                <CommandGroup>
+                  {profiles.map((profile) => (
+                    <CommandItem
+                      key={profile.name}
+                      value={profile.name}
+                      onSelect={() => {
+                        onSelect(profile.name);
+                        setPopoverOpen(false);
                       }}
+                      className="flex justify-between items-center"
                     >
-                      <Trash className="h-4 w-4 text-destructive" />
-                    </Button>
-                  </CommandItem>
-                ))}
-              </CommandGroup>
-            </CommandList>
-            <div className="p-2 border-t">
-              <Button
-                variant="outline"
-                className="w-full"
-                onClick={() => setDialogOpen(true)}
-              >
-                <Plus className="mr-2 h-4 w-4" />
-                Save Current Connection
+                      <div className="flex flex-col min-w-0">
+                        <span className="truncate">{profile.name}</span>
+                        <span className="truncate text-xs text-muted-foreground">
+                          {describeProfile(profile)}
+                        </span>
+                      </div>
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        className="h-6 w-6"
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          onDelete(profile.name);
+                        }}
+                      >
+                        <Trash className="h-4 w-4 text-destructive" />
+                      </Button>
+                    </CommandItem>
+                  ))}
+                </CommandGroup>
+              </CommandList>
+              <div className="p-2 border-t flex flex-col gap-2">
+                <Button
+                  variant="outline"
+                  className="w-full"
+                  disabled={!currentUri}
+                  onClick={() => setDialogOpen(true)}
+                >
+                  <Plus className="mr-2 h-4 w-4" />
+                  Save Current Connection
+                </Button>
+                <Button variant="ghost" className="w-full" onClick={onLock}>
+                  <Lock className="mr-2 h-4 w-4" />
+                  Lock Vault
+                </Button>
+              </div>
+            </Command>
+          ) : (
+            // saved connections live in an encrypted vault; the first password
+            // given creates it
+            <form onSubmit={handleUnlock} className="p-2 flex flex-col gap-2">
+              <Input
+                type="password"
+                placeholder={
+                  vault?.exists ? "Vault Password" : "New Vault Password"
+                }
+                value={password}
+                onChange={(e) => setPassword(e.target.value)}
+              />
+              {error && <p className="text-xs text-destructive">{error}</p>}
+              <Button type="submit" disabled={!password}>
+                <LockOpen className="mr-2 h-4 w-4" />
+                {vault?.exists ? "Unlock" : "Create Vault"}
               </Button>
-            </div>
-          </Command>
+            </form>
+          )}
         </PopoverContent>
       </Popover>
       <Dialog open={dialogOpen} onOpenChange={setDialogOpen}>
@@ -121,6 +205,7 @@ export function ConnectionManager({
               onChange={(e) => setNewConnectionName(e.target.value)}
             />
             <Input value={currentUri} readOnly disabled />
+            {error && <p className="text-sm text-destructive">{error}</p>}
           </div>
           <DialogFooter>
             <Button onClick={handleSave}>Save</Button>
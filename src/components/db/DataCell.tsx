@@ -320,7 +320,11 @@ const DataCell = ({
     return value;
   }, [value]);
 
-  const isVectorValue = useMemo(() => isVector(parsedValue), [parsedValue]);
+  // an int4[] or float8[] column holds number arrays too, which aren't vectors
+  const isVectorValue = useMemo(
+    () => columnType === "vector" && isVector(parsedValue),
+    [columnType, parsedValue]
+  );
   const isJsonObject = useMemo(
     () =>
       typeof parsedValue === "object" && parsedValue !== null && !isVectorValue,
@@ -349,7 +353,12 @@ const DataCell = ({
   );
 
   if (isEditing) {
-    if (columnType === "json" || columnType === "jsonb") {
+    // array columns have udt names like _int4 and are edited as json arrays
+    if (
+      columnType === "json" ||
+      columnType === "jsonb" ||
+      columnType.startsWith("_")
+    ) {
       return (
         <>
           {cellDisplay}
@@ -514,4 +523,34 @@ const DataCell = ({
   return cellWithMenu;
 };
 
+// a value the backend couldn't decode; shown as the server's text for it and
+// never editable, so it can't be mistaken for NULL and overwritten
+export const UndecodableCell = ({
+  pgType,
+  text,
+}: {
+  pgType: string;
+  text: string | null;
+}) => (
+  <TooltipProvider>
+    <Tooltip>
+      <TooltipTrigger asChild>
+        <div className="truncate h-full flex items-center max-w-[250px] font-mono text-muted-foreground cursor-not-allowed">
+          {text ?? <i>{`<${pgType}>`}</i>}
+        </div>
+      </TooltipTrigger>
+      <TooltipContent
+        side="bottom"
+        align="start"
+        className="max-w-md break-all"
+      >
+        {`${pgType} values can't be decoded, so this cell is read-only`}
+        {text !== null && text.length > 50 && (
+          <div className="font-mono mt-1">{text}</div>
+        )}
+      </TooltipContent>
+    </Tooltip>
+  </TooltipProvider>
+);
+
 export default DataCell;
@@ -19,16 +19,48 @@ const operators = [
   "<",
   ">=",
   "<=",
+  "LIKE",
+  "NOT LIKE",
+  "NOT ILIKE",
+  "IN",
+  "NOT IN",
+  "BETWEEN",
+  "NOT BETWEEN",
   "IS NULL",
   "IS NOT NULL",
 ];
 
+const noValueOperators = ["IS NULL", "IS NOT NULL"];
+const listOperators = ["IN", "NOT IN", "BETWEEN", "NOT BETWEEN"];
+
 export type RichFilter = {
   id: string;
   operator: string;
   value: string;
 };
 
+// IN and BETWEEN take their values as a comma separated list
+export function toBackendFilter(filter: RichFilter) {
+  const condition = {
+    type: "condition",
+    column: filter.id,
+    operator: filter.operator,
+  };
+  if (noValueOperators.includes(filter.operator)) {
+    return condition;
+  }
+  if (listOperators.includes(filter.operator)) {
+    return {
+      ...condition,
+      values: filter.value
+        .split(",")
+        .map((value) => value.trim())
+        .filter((value) => value !== ""),
+    };
+  }
+  return { ...condition, value: filter.value };
+}
+
 type FilterBuilderProps<TData> = {
   columns: Column<TData, unknown>[];
   filters: RichFilter[];
@@ -109,8 +141,14 @@ export function FilterBuilder<TData>({
       </div>
       <div className="space-y-2">
         {filters.map((filter, index) => {
-          const operatorRequiresValue =
-            filter.operator !== "IS NULL" && filter.operator !== "IS NOT NULL";
+          const operatorRequiresValue = !noValueOperators.includes(
+            filter.operator
+          );
+          const placeholder = filter.operator.endsWith("BETWEEN")
+            ? "low, high"
+            : listOperators.includes(filter.operator)
+              ? "value, value, ..."
+              : "Filter value";
           return (
             <div key={index} className="flex items-center gap-2">
               <Select
@@ -150,7 +188,7 @@ export function FilterBuilder<TData>({
               <Input
                 value={filter.value}
                 onChange={(e) => handleValueChange(index, e.target.value)}
-                placeholder="Filter value"
+                placeholder={placeholder}
                 className="flex-grow"
                 disabled={!operatorRequiresValue}
               />